@@ -0,0 +1,725 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::{
+  codec::Id as AvCodecId,
+  codec::packet::Packet as AvPacket,
+  util::error::EAGAIN,
+  Error as AvError,
+};
+
+use super::{
+  Error,
+  Packet,
+  StreamInfo,
+  extradata::{
+    avcc_from_parameter_sets,
+    extract_parameter_sets_h264,
+    extract_parameter_sets_hevc,
+    find_avc_start_code,
+    hvcc_from_parameter_sets,
+    is_hvcc,
+  },
+  ffi::{
+    codec_parameters_extradata,
+    set_codec_parameters_extradata,
+    BitstreamFilterContext,
+  },
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Format of an H.264 or H.265/HEVC bitstream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitstreamFormat {
+  /// NAL units are prefixed with their length. Used by MP4 and Matroska,
+  /// where the parameter sets are stored out-of-band in the `avcC` or
+  /// `hvcC` record. (This format is called AVCC for H.264 and HVCC for
+  /// H.265/HEVC.)
+  LengthPrefixed,
+  /// NAL units are prefixed with start codes, as defined in Annex B of
+  /// the Recommendation H.264. Used by MPEG-TS and raw H.264 streams,
+  /// where the parameter sets are sent in-band.
+  AnnexB,
+}
+
+/// Converts the packets of an H.264 or H.265/HEVC stream between the
+/// length-prefixed format and the Annex B format. The format of the source
+/// stream is derived from its extradata.
+/// 
+/// When converting to Annex B, the parameter sets from the extradata are
+/// inserted in front of each keyframe (unless the keyframe already carries
+/// them), after the access unit delimiter if there is one, so that
+/// consumers can start decoding at any keyframe. When converting to the
+/// length-prefixed format, NAL units are prefixed with a four byte length
+/// and an `avcC` or `hvcC` record is built from the parameter sets.
+/// 
+/// The converted packets need different extradata than the source stream,
+/// so the output stream must be set up with `stream_info` of the converter.
+/// 
+/// # Examples
+/// 
+/// ```
+/// let mut reader = Reader::new(&PathBuf::from("my_video.mp4").into())
+///   .unwrap();
+/// let stream_index = reader.best_video_stream_index().unwrap();
+/// let mut converter = BitstreamConverter::new(
+///     &reader.stream_info(stream_index).unwrap(),
+///     BitstreamFormat::AnnexB)
+///   .unwrap();
+/// let packet = converter
+///   .convert(reader.read(stream_index).unwrap())
+///   .unwrap();
+/// ```
+pub struct BitstreamConverter {
+  stream_info: StreamInfo,
+  conversion: Conversion,
+}
+
+/// Conversion to perform, determined by source and target format.
+enum Conversion {
+  Passthrough,
+  ToAnnexB {
+    nal_length_size: usize,
+    codec: Codec,
+    parameter_sets: Vec<u8>,
+  },
+  ToLengthPrefixed {
+    codec: Codec,
+    record: Option<Vec<u8>>,
+  },
+}
+
+/// Codecs that have a length-prefixed and Annex B format.
+#[derive(Clone, Copy)]
+enum Codec {
+  H264,
+  Hevc,
+}
+
+impl BitstreamConverter {
+
+  /// Create a converter for packets from the given stream.
+  /// 
+  /// Note that this only supports streams with the H.264 and H.265/HEVC
+  /// codecs and will return `Error::UnsupportedBitstreamConversion` for
+  /// streams with another type of codec.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `stream_info` - Information of stream that packets come from.
+  /// * `target` - Format to convert packets to.
+  pub fn new(
+    stream_info: &StreamInfo,
+    target: BitstreamFormat,
+  ) -> Result<Self> {
+    let parameters = stream_info.codec_parameters();
+    let codec = match parameters.id() {
+      AvCodecId::H264 => Codec::H264,
+      AvCodecId::HEVC => Codec::Hevc,
+      _ => return Err(Error::UnsupportedBitstreamConversion),
+    };
+
+    let extradata = codec_parameters_extradata(parameters);
+    let source = source_format(codec, extradata);
+
+    let conversion = match (source, target) {
+      (BitstreamFormat::LengthPrefixed, BitstreamFormat::AnnexB) => {
+        let parameter_sets = match codec {
+          Codec::H264 => {
+            let (sps, pps) = extract_parameter_sets_h264(extradata)?;
+            annexb(std::iter::once(sps).chain(pps))
+          },
+          Codec::Hevc => {
+            let parameter_sets = extract_parameter_sets_hevc(extradata)?;
+            annexb(parameter_sets.vps
+              .into_iter()
+              .chain(parameter_sets.sps)
+              .chain(parameter_sets.pps))
+          },
+        };
+
+        Conversion::ToAnnexB {
+          nal_length_size: nal_length_size(codec, extradata)?,
+          codec,
+          parameter_sets,
+        }
+      },
+      (BitstreamFormat::AnnexB, BitstreamFormat::LengthPrefixed) =>
+        // Streams without extradata only have in-band parameter sets, in
+        // which case the record is built from the first packet that has
+        // them.
+        Conversion::ToLengthPrefixed {
+          codec,
+          record: build_record(codec, extradata).ok(),
+        },
+      _ => Conversion::Passthrough,
+    };
+
+    Ok(Self {
+      stream_info: stream_info.clone(),
+      conversion,
+    })
+  }
+
+  /// Information of the converted stream. This is the same as the source
+  /// stream, except for the extradata, which matches the target format.
+  /// 
+  /// When converting to the length-prefixed format from a stream that has
+  /// no parameter sets in its extradata, this returns
+  /// `Error::MissingCodecParameters` until a packet with parameter sets
+  /// has been converted.
+  pub fn stream_info(&self) -> Result<StreamInfo> {
+    let extradata = match self.conversion {
+      Conversion::Passthrough =>
+        return Ok(self.stream_info.clone()),
+      Conversion::ToAnnexB { ref parameter_sets, .. } =>
+        parameter_sets,
+      Conversion::ToLengthPrefixed { record: Some(ref record), .. } =>
+        record,
+      Conversion::ToLengthPrefixed { record: None, .. } =>
+        return Err(Error::MissingCodecParameters),
+    };
+
+    let (index, mut codec_parameters, time_base) =
+      self.stream_info.clone().into_parts();
+    set_codec_parameters_extradata(&mut codec_parameters, extradata)?;
+
+    Ok(StreamInfo::new(index, codec_parameters, time_base))
+  }
+
+  /// Convert a single packet. Packet properties like timestamps, flags
+  /// and stream index are kept.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `packet` - Packet to convert.
+  pub fn convert(&mut self, packet: Packet) -> Result<Packet> {
+    match self.conversion {
+      Conversion::Passthrough => Ok(packet),
+      Conversion::ToAnnexB { nal_length_size, codec, ref parameter_sets } => {
+        let parameter_sets = if packet.is_key() {
+          Some(parameter_sets.as_slice())
+        } else {
+          None
+        };
+        packet.with_data(&to_annexb(
+          packet.data(),
+          nal_length_size,
+          codec,
+          parameter_sets)?)
+      },
+      Conversion::ToLengthPrefixed { codec, ref mut record } => {
+        if record.is_none() {
+          *record = build_record(codec, packet.data()).ok();
+        }
+
+        packet.with_data(&to_length_prefixed(packet.data()))
+      },
+    }
+  }
+
+}
+
+/// Applies one or more of the backend's bitstream filters to the packets
+/// of a stream, like `dump_extra`, `extract_extradata`, `filter_units` or
+/// `h264_metadata`. Bitstream filters modify packets without decoding
+/// them. Some filters also change the codec parameters of the stream, so
+/// the output stream must be set up with `stream_info` of the filter.
+/// 
+/// To filter packets before muxing them, pass the filter to
+/// `Muxer::with_filtered_stream`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// let mut reader = Reader::new(&PathBuf::from("my_video.mp4").into())
+///   .unwrap();
+/// let stream_index = reader.best_video_stream_index().unwrap();
+/// let mut filter = BitstreamFilter::new(
+///     &reader.stream_info(stream_index).unwrap(),
+///     "h264_mp4toannexb,h264_metadata=aud=insert")
+///   .unwrap();
+/// for packet in filter.filter(reader.read(stream_index).unwrap()).unwrap() {
+///   // ...
+/// }
+/// ```
+pub struct BitstreamFilter {
+  context: BitstreamFilterContext,
+  stream_index: usize,
+}
+
+impl BitstreamFilter {
+
+  /// Create a bitstream filter for packets from the given stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `stream_info` - Information of stream that packets come from.
+  /// * `filters` - Comma separated list of filters, each optionally
+  ///   followed by `=` and options separated by `:`, like
+  ///   `filter_units=remove_types=6,dump_extra`.
+  pub fn new(
+    stream_info: &StreamInfo,
+    filters: &str,
+  ) -> Result<Self> {
+    let context = BitstreamFilterContext::new(
+      filters,
+      stream_info.codec_parameters(),
+      stream_info.time_base())?;
+
+    Ok(Self {
+      context,
+      stream_index: stream_info.index,
+    })
+  }
+
+  /// Information of the filtered stream. This has the same stream index as
+  /// the source stream, but the codec parameters and time base produced by
+  /// the filter.
+  pub fn stream_info(&self) -> StreamInfo {
+    StreamInfo::new(
+      self.stream_index,
+      self.context.parameters_out(),
+      self.context.time_base_out(),
+    )
+  }
+
+  /// Filter a single packet. Filters may hold on to packets, or produce
+  /// more than one packet per input packet, so this returns zero or more
+  /// packets.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `packet` - Packet to filter.
+  pub fn filter(&mut self, packet: Packet) -> Result<Vec<Packet>> {
+    let mut packet = packet.into_inner();
+    self.context.send(Some(&mut packet))?;
+    self.receive_all()
+  }
+
+  /// Signal the end of the stream to the filter, and return any packets it
+  /// was still holding on to. After flushing, no more packets can be
+  /// filtered.
+  pub fn flush(&mut self) -> Result<Vec<Packet>> {
+    self.context.send(None)?;
+    self.receive_all()
+  }
+
+  /// Receive packets until the filter needs more input or is exhausted.
+  fn receive_all(&mut self) -> Result<Vec<Packet>> {
+    let time_base = self.context.time_base_out();
+    let mut packets = Vec::new();
+    loop {
+      let mut packet = AvPacket::empty();
+      match self.context.receive(&mut packet) {
+        Ok(()) =>
+          packets.push(Packet::new(packet, time_base)),
+        Err(AvError::Eof) | Err(AvError::Other { errno: EAGAIN }) =>
+          break Ok(packets),
+        Err(err) =>
+          break Err(err.into()),
+      }
+    }
+  }
+
+}
+
+/// Determine the format of a stream from its extradata. `avcC` and `hvcC`
+/// records are only used with length-prefixed streams. Streams without
+/// extradata carry their parameter sets in-band, so they are Annex B.
+/// 
+/// # Arguments
+/// 
+/// * `codec` - Codec of stream.
+/// * `extradata` - Extradata of stream.
+fn source_format(codec: Codec, extradata: &[u8]) -> BitstreamFormat {
+  let is_record = match codec {
+    Codec::H264 => extradata.first() == Some(&0x01),
+    Codec::Hevc => is_hvcc(extradata),
+  };
+
+  if is_record {
+    BitstreamFormat::LengthPrefixed
+  } else {
+    BitstreamFormat::AnnexB
+  }
+}
+
+/// Convert length-prefixed packet data to Annex B.
+/// 
+/// # Arguments
+/// 
+/// * `bytes` - Length-prefixed packet data.
+/// * `nal_length_size` - Size of length prefix in bytes.
+/// * `codec` - Codec of stream.
+/// * `parameter_sets` - Parameter sets in Annex B format to insert, unless
+///   the packet already carries them.
+fn to_annexb(
+  bytes: &[u8],
+  nal_length_size: usize,
+  codec: Codec,
+  parameter_sets: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+  let nals = split_length_prefixed(bytes, nal_length_size)?;
+  let parameter_sets = parameter_sets
+    .filter(|_| !nals.iter().any(|nal| is_parameter_set(codec, nal)))
+    .unwrap_or(&[]);
+
+  // An access unit delimiter must be the first NAL unit of an access unit,
+  // so parameter sets are inserted after it.
+  let (delimiters, nals) = nals.split_at(
+    nals
+      .iter()
+      .take_while(|nal| is_access_unit_delimiter(codec, nal))
+      .count());
+
+  let mut data = Vec::with_capacity(bytes.len() + parameter_sets.len());
+  data.extend_from_slice(&annexb(delimiters.iter().copied()));
+  data.extend_from_slice(parameter_sets);
+  data.extend_from_slice(&annexb(nals.iter().copied()));
+  Ok(data)
+}
+
+/// Convert Annex B packet data to the length-prefixed format, with four
+/// byte lengths.
+/// 
+/// # Arguments
+/// 
+/// * `bytes` - Annex B packet data.
+fn to_length_prefixed(bytes: &[u8]) -> Vec<u8> {
+  let mut data = Vec::with_capacity(bytes.len());
+  for nal in split_annexb(bytes) {
+    data.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+    data.extend_from_slice(nal);
+  }
+  data
+}
+
+/// Build an `avcC` or `hvcC` record from the parameter sets in Annex B
+/// data.
+/// 
+/// # Arguments
+/// 
+/// * `codec` - Codec of stream.
+/// * `bytes` - Annex B data, like extradata or packet data.
+fn build_record(codec: Codec, bytes: &[u8]) -> Result<Vec<u8>> {
+  match codec {
+    Codec::H264 => {
+      let (sps, pps) = extract_parameter_sets_h264(bytes)?;
+      avcc_from_parameter_sets(sps, &pps)
+    },
+    Codec::Hevc =>
+      hvcc_from_parameter_sets(&extract_parameter_sets_hevc(bytes)?),
+  }
+}
+
+/// Get the size in bytes of the NAL unit length prefix from an `avcC` or
+/// `hvcC` record.
+/// 
+/// # Arguments
+/// 
+/// * `codec` - Codec of stream.
+/// * `extradata` - Extradata of stream in `avcC` or `hvcC` format.
+fn nal_length_size(codec: Codec, extradata: &[u8]) -> Result<usize> {
+  let offset = match codec {
+    Codec::H264 => 4,
+    Codec::Hevc => 21,
+  };
+
+  extradata
+    .get(offset)
+    .map(|byte| (byte & 0x03) as usize + 1)
+    .ok_or(Error::InvalidExtraData)
+}
+
+/// Whether or not a NAL unit is a parameter set (VPS, SPS or PPS).
+/// 
+/// # Arguments
+/// 
+/// * `codec` - Codec of stream.
+/// * `nal` - NAL unit including header.
+fn is_parameter_set(codec: Codec, nal: &[u8]) -> bool {
+  match (codec, nal.first()) {
+    (Codec::H264, Some(header)) =>
+      matches!(header & 0x1f, 0x07 /* SPS */ | 0x08 /* PPS */),
+    (Codec::Hevc, Some(header)) =>
+      matches!((header >> 1) & 0x3f, 32 /* VPS */ ..= 34 /* PPS */),
+    _ => false,
+  }
+}
+
+/// Whether or not a NAL unit is an access unit delimiter (AUD).
+/// 
+/// # Arguments
+/// 
+/// * `codec` - Codec of stream.
+/// * `nal` - NAL unit including header.
+fn is_access_unit_delimiter(codec: Codec, nal: &[u8]) -> bool {
+  match (codec, nal.first()) {
+    (Codec::H264, Some(header)) => header & 0x1f == 0x09,
+    (Codec::Hevc, Some(header)) => (header >> 1) & 0x3f == 35,
+    _ => false,
+  }
+}
+
+/// Write NAL units in Annex B format, each preceded by a start code.
+/// 
+/// # Arguments
+/// 
+/// * `nals` - NAL units to write.
+fn annexb<'buf>(nals: impl Iterator<Item = &'buf [u8]>) -> Vec<u8> {
+  let mut data = Vec::new();
+  for nal in nals {
+    data.extend_from_slice(&[0x00, 0x00, 0x00, 0x01]);
+    data.extend_from_slice(nal);
+  }
+  data
+}
+
+/// Split length-prefixed data into NAL units.
+/// 
+/// # Arguments
+/// 
+/// * `bytes` - Length-prefixed data.
+/// * `nal_length_size` - Size of length prefix in bytes.
+fn split_length_prefixed(
+  bytes: &[u8],
+  nal_length_size: usize,
+) -> Result<Vec<&[u8]>> {
+  let mut nals = Vec::new();
+  let mut p = 0;
+  while p < bytes.len() {
+    if bytes[p..].len() < nal_length_size {
+      return Err(Error::InvalidBitstream);
+    }
+
+    let nal_size = bytes[p..p + nal_length_size]
+      .iter()
+      .fold(0_usize, |size, byte| (size << 8) | *byte as usize);
+    p += nal_length_size;
+
+    if bytes[p..].len() < nal_size {
+      return Err(Error::InvalidBitstream);
+    }
+
+    nals.push(&bytes[p..p + nal_size]);
+    p += nal_size;
+  }
+
+  Ok(nals)
+}
+
+/// Split Annex B data into NAL units. Any data before the first start
+/// code is ignored.
+/// 
+/// # Arguments
+/// 
+/// * `bytes` - Annex B data.
+fn split_annexb(bytes: &[u8]) -> Vec<&[u8]> {
+  let mut nals = Vec::new();
+  let mut index_current = find_avc_start_code(bytes, 0)
+    .map(|(_, index_next)| index_next);
+
+  while let Some(index) = index_current {
+    let (end, index_next) = match find_avc_start_code(bytes, index) {
+      Some((end, index_next))
+        => (end, Some(index_next)),
+      None
+        => (bytes.len(), None)
+    };
+    if end > index {
+      nals.push(&bytes[index..end]);
+    }
+
+    index_current = index_next;
+  }
+
+  nals
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SPS_H264: &[u8] = &[
+    0x67, 0x64, 0x00, 0x1f, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0x84,
+    0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xca, 0x10,
+  ];
+  const PPS_H264: &[u8] = &[0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0];
+  const AUD_H264: &[u8] = &[0x09, 0xf0];
+  const IDR_H264: &[u8] = &[0x65, 0x88, 0x84, 0x00, 0x33, 0xff];
+  const NON_IDR_H264: &[u8] = &[0x41, 0x9a, 0x02, 0x04];
+
+  const VPS_HEVC: &[u8] = &[0x40, 0x01, 0x0c, 0x01, 0xff, 0xff];
+  const SPS_HEVC: &[u8] = &[
+    0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00,
+    0x03, 0x00, 0x00, 0x03, 0x00, 0x5d, 0xa0, 0x03, 0xc0, 0x80, 0x10, 0xe5,
+    0x96,
+  ];
+  const PPS_HEVC: &[u8] = &[0x44, 0x01, 0xc1, 0x72, 0xb4, 0x62, 0x40];
+  const AUD_HEVC: &[u8] = &[0x46, 0x01, 0x10];
+  const IDR_HEVC: &[u8] = &[0x26, 0x01, 0xaf, 0x06, 0xb8];
+
+  fn length_prefixed(nals: &[&[u8]], nal_length_size: usize) -> Vec<u8> {
+    let mut data = Vec::new();
+    for nal in nals {
+      let size = (nal.len() as u32).to_be_bytes();
+      data.extend_from_slice(&size[4 - nal_length_size..]);
+      data.extend_from_slice(nal);
+    }
+    data
+  }
+
+  #[test]
+  fn length_prefixed_to_annexb_and_back() {
+    let nals = [AUD_H264, NON_IDR_H264, NON_IDR_H264];
+    for nal_length_size in [1, 2, 4] {
+      let data = to_annexb(
+          &length_prefixed(&nals, nal_length_size),
+          nal_length_size,
+          Codec::H264,
+          None)
+        .unwrap();
+      assert_eq!(data, annexb(nals.iter().copied()));
+      assert_eq!(split_annexb(&data), nals);
+      assert_eq!(to_length_prefixed(&data), length_prefixed(&nals, 4));
+    }
+  }
+
+  #[test]
+  fn split_annexb_three_and_four_byte_start_codes() {
+    let mut data = vec![0x00, 0x00, 0x01];
+    data.extend_from_slice(AUD_H264);
+    data.extend_from_slice(&[0x00, 0x00, 0x00, 0x01]);
+    data.extend_from_slice(IDR_H264);
+    assert_eq!(split_annexb(&data), [AUD_H264, IDR_H264]);
+  }
+
+  #[test]
+  fn to_annexb_inserts_parameter_sets_after_delimiter() {
+    let parameter_sets = annexb([SPS_H264, PPS_H264].into_iter());
+    let data = to_annexb(
+        &length_prefixed(&[AUD_H264, IDR_H264], 4),
+        4,
+        Codec::H264,
+        Some(&parameter_sets))
+      .unwrap();
+    assert_eq!(
+      split_annexb(&data),
+      [AUD_H264, SPS_H264, PPS_H264, IDR_H264]);
+
+    let parameter_sets = annexb([VPS_HEVC, SPS_HEVC, PPS_HEVC].into_iter());
+    let data = to_annexb(
+        &length_prefixed(&[AUD_HEVC, IDR_HEVC], 4),
+        4,
+        Codec::Hevc,
+        Some(&parameter_sets))
+      .unwrap();
+    assert_eq!(
+      split_annexb(&data),
+      [AUD_HEVC, VPS_HEVC, SPS_HEVC, PPS_HEVC, IDR_HEVC]);
+  }
+
+  #[test]
+  fn to_annexb_keeps_in_band_parameter_sets() {
+    let parameter_sets = annexb([SPS_H264, PPS_H264].into_iter());
+    let nals = [SPS_H264, PPS_H264, IDR_H264];
+    let data = to_annexb(
+        &length_prefixed(&nals, 4),
+        4,
+        Codec::H264,
+        Some(&parameter_sets))
+      .unwrap();
+    assert_eq!(split_annexb(&data), nals);
+  }
+
+  #[test]
+  fn split_length_prefixed_truncated() {
+    // Length prefix cut off.
+    assert!(matches!(
+      split_length_prefixed(&[0x00, 0x00], 4),
+      Err(Error::InvalidBitstream)));
+    // NAL unit shorter than its length.
+    assert!(matches!(
+      split_length_prefixed(&[0x00, 0x00, 0x00, 0x05, 0x65, 0x88], 4),
+      Err(Error::InvalidBitstream)));
+    // Second NAL unit truncated.
+    let mut data = length_prefixed(&[IDR_H264], 2);
+    data.extend_from_slice(&[0x00, 0x04, 0x41]);
+    assert!(matches!(
+      split_length_prefixed(&data, 2),
+      Err(Error::InvalidBitstream)));
+    assert!(matches!(
+      to_annexb(&data, 2, Codec::H264, None),
+      Err(Error::InvalidBitstream)));
+  }
+
+  #[test]
+  fn build_avcc_record() {
+    let extradata = annexb([SPS_H264, PPS_H264].into_iter());
+    assert_eq!(
+      source_format(Codec::H264, &extradata),
+      BitstreamFormat::AnnexB);
+
+    let record = build_record(Codec::H264, &extradata).unwrap();
+    assert_eq!(record[..6], [0x01, 0x64, 0x00, 0x1f, 0xff, 0xe1]);
+    // Chroma format and bit depth of the high profile.
+    assert_eq!(record[record.len() - 4..], [0xfd, 0xf8, 0xf8, 0x00]);
+    assert_eq!(
+      source_format(Codec::H264, &record),
+      BitstreamFormat::LengthPrefixed);
+    assert_eq!(nal_length_size(Codec::H264, &record).unwrap(), 4);
+
+    let (sps, pps) = extract_parameter_sets_h264(&record).unwrap();
+    assert_eq!(sps, SPS_H264);
+    assert_eq!(pps, [PPS_H264]);
+  }
+
+  #[test]
+  fn build_hvcc_record() {
+    let extradata = annexb([VPS_HEVC, SPS_HEVC, PPS_HEVC].into_iter());
+    assert_eq!(
+      source_format(Codec::Hevc, &extradata),
+      BitstreamFormat::AnnexB);
+
+    let record = build_record(Codec::Hevc, &extradata).unwrap();
+    // General profile, tier and level without emulation prevention bytes.
+    assert_eq!(
+      record[..13],
+      [
+        0x01, 0x01, 0x60, 0x00, 0x00, 0x00, 0xb0, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x5d,
+      ]);
+    // Chroma format, bit depths, one temporal layer with nesting and
+    // three arrays.
+    assert_eq!(record[16..19], [0xfd, 0xf8, 0xf8]);
+    assert_eq!(record[21..23], [0x0f, 0x03]);
+    assert_eq!(
+      source_format(Codec::Hevc, &record),
+      BitstreamFormat::LengthPrefixed);
+    assert_eq!(nal_length_size(Codec::Hevc, &record).unwrap(), 4);
+
+    let parameter_sets = extract_parameter_sets_hevc(&record).unwrap();
+    assert_eq!(parameter_sets.vps, [VPS_HEVC]);
+    assert_eq!(parameter_sets.sps, [SPS_HEVC]);
+    assert_eq!(parameter_sets.pps, [PPS_HEVC]);
+  }
+
+  #[test]
+  fn build_record_requires_parameter_sets() {
+    assert!(build_record(
+        Codec::H264,
+        &annexb([SPS_H264].into_iter()))
+      .is_err());
+    assert!(build_record(Codec::H264, &[]).is_err());
+    assert!(build_record(
+        Codec::Hevc,
+        &annexb([SPS_HEVC, PPS_HEVC].into_iter()))
+      .is_err());
+    assert!(build_record(
+        Codec::H264,
+        &annexb([AUD_H264, NON_IDR_H264].into_iter()))
+      .is_err());
+  }
+
+}
@@ -0,0 +1,354 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::codec::{
+  Id as AvCodecId,
+  Parameters as AvCodecParameters,
+};
+
+use super::{
+  Error,
+  SequenceParameterSet,
+  extradata::{
+    extract_parameter_sets_h264,
+    extract_parameter_sets_hevc,
+    is_hvcc,
+  },
+  sps::remove_emulation_prevention,
+  ffi::{
+    codec_parameters_extradata,
+    codec_parameters_profile_and_level,
+    codec_parameters_codec_tag,
+    codec_parameters_dimensions,
+    codec_parameters_bit_depth,
+  },
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Produce the codec string as defined in RFC 6381 for a stream, like
+/// `avc1.64001f` or `mp4a.40.2`. This is the value that goes in the
+/// `codecs` parameter of a MIME type, as required by Media Source
+/// Extensions.
+/// 
+/// Supported codecs are H.264, H.265/HEVC, VP9, AV1, AAC and Opus. For
+/// other codecs `Error::UnsupportedCodecString` is returned.
+/// 
+/// # Arguments
+/// 
+/// * `parameters` - Codec parameters of stream.
+pub(crate) fn codec_string(parameters: &AvCodecParameters) -> Result<String> {
+  let extradata = codec_parameters_extradata(parameters);
+  match parameters.id() {
+    AvCodecId::H264 => codec_string_h264(extradata),
+    AvCodecId::HEVC => codec_string_hevc(
+      extradata,
+      codec_parameters_codec_tag(parameters)),
+    AvCodecId::VP9 => Ok(codec_string_vp9(parameters)),
+    AvCodecId::AV1 => codec_string_av1(extradata),
+    AvCodecId::AAC => codec_string_aac(
+      extradata,
+      codec_parameters_profile_and_level(parameters).0),
+    AvCodecId::OPUS => Ok("opus".to_string()),
+    _ => Err(Error::UnsupportedCodecString),
+  }
+}
+
+/// Produce H.264 codec string `avc1.PPCCLL`, where `PP`, `CC` and `LL` are
+/// the profile, constraint set flags and level from the SPS in hex.
+/// 
+/// # Arguments
+/// 
+/// * `extradata` - Extradata of stream.
+fn codec_string_h264(extradata: &[u8]) -> Result<String> {
+  let (sps, _) = extract_parameter_sets_h264(extradata)?;
+  let sps = SequenceParameterSet::parse(sps)?;
+
+  Ok(format!(
+    "avc1.{:02x}{:02x}{:02x}",
+    sps.profile_idc,
+    sps.constraint_set_flags,
+    sps.level_idc,
+  ))
+}
+
+/// Produce H.265/HEVC codec string as defined in Annex E of ISO/IEC
+/// 14496-15, like `hvc1.1.6.L93.B0`, from the general profile, tier and
+/// level. These are taken from the `hvcC` record, or from the SPS if the
+/// extradata is in Annex B format.
+/// 
+/// # Arguments
+/// 
+/// * `extradata` - Extradata of stream.
+/// * `codec_tag` - Codec tag of stream, to tell `hev1` from `hvc1`.
+fn codec_string_hevc(extradata: &[u8], codec_tag: u32) -> Result<String> {
+  const HEV1: u32 = u32::from_le_bytes(*b"hev1");
+
+  // The general profile, tier and level is 12 bytes: profile space, tier
+  // and profile (1 byte), profile compatibility flags (4 bytes),
+  // constraint indicator flags (6 bytes) and level (1 byte).
+  let profile_tier_level = if is_hvcc(extradata) {
+    extradata
+      .get(1..13)
+      .ok_or(Error::InvalidExtraData)?
+      .to_vec()
+  } else {
+    let parameter_sets = extract_parameter_sets_hevc(extradata)?;
+    let sps = parameter_sets.sps[0];
+    // Skip the two byte NAL unit header and the byte holding the VPS id,
+    // maximum number of sub-layers and temporal id nesting flag.
+    remove_emulation_prevention(sps.get(2..).ok_or(Error::InvalidExtraData)?)
+      .get(1..13)
+      .ok_or(Error::InvalidExtraData)?
+      .to_vec()
+  };
+
+  let profile_space = profile_tier_level[0] >> 6;
+  let tier = (profile_tier_level[0] >> 5) & 0x01;
+  let profile_idc = profile_tier_level[0] & 0x1f;
+  let compatibility_flags = u32::from_be_bytes([
+    profile_tier_level[1],
+    profile_tier_level[2],
+    profile_tier_level[3],
+    profile_tier_level[4],
+  ]);
+  let level_idc = profile_tier_level[11];
+
+  let prefix = if codec_tag == HEV1 {
+    "hev1"
+  } else {
+    "hvc1"
+  };
+
+  let mut codec_string = format!(
+    "{}.{}{}.{:x}.{}{}",
+    prefix,
+    ["", "A", "B", "C"][profile_space as usize],
+    profile_idc,
+    // Compatibility flags are written in reverse bit order.
+    compatibility_flags.reverse_bits(),
+    if tier == 0 { 'L' } else { 'H' },
+    level_idc,
+  );
+
+  // Constraint indicator flags are written per byte, omitting trailing
+  // zero bytes.
+  let constraint_flags = &profile_tier_level[5..11];
+  let num_constraint_bytes = constraint_flags
+    .iter()
+    .rposition(|byte| *byte != 0)
+    .map(|position| position + 1)
+    .unwrap_or(0);
+  for byte in &constraint_flags[..num_constraint_bytes] {
+    codec_string.push_str(&format!(".{:X}", byte));
+  }
+
+  Ok(codec_string)
+}
+
+/// Produce VP9 codec string `vp09.PP.LL.DD` as defined by the VP codec ISO
+/// media file format binding. The backend does not keep the `vpcC` record,
+/// so the level is derived from the picture size if it is unknown.
+fn codec_string_vp9(parameters: &AvCodecParameters) -> String {
+  let (profile, level) = codec_parameters_profile_and_level(parameters);
+  let profile = profile.max(0);
+  let level = if level > 0 {
+    level as u32
+  } else {
+    let (width, height) = codec_parameters_dimensions(parameters);
+    vp9_level_for_picture_size(width as u64 * height as u64)
+  };
+  let bit_depth = codec_parameters_bit_depth(parameters).unwrap_or(8);
+
+  format!("vp09.{:02}.{:02}.{:02}", profile, level, bit_depth)
+}
+
+/// Produce AV1 codec string `av01.P.LLT.DD` as defined by the AV1 codec ISO
+/// media file format binding, from the `av1C` record.
+/// 
+/// # Arguments
+/// 
+/// * `extradata` - Extradata of stream.
+fn codec_string_av1(extradata: &[u8]) -> Result<String> {
+  // The first byte holds the marker bit and version, which must both be 1.
+  if extradata.len() < 4 || extradata[0] != 0x81 {
+    return Err(Error::InvalidExtraData);
+  }
+
+  let seq_profile = extradata[1] >> 5;
+  let seq_level_idx = extradata[1] & 0x1f;
+  let seq_tier = (extradata[2] >> 7) & 0x01;
+  let high_bitdepth = (extradata[2] >> 6) & 0x01 == 1;
+  let twelve_bit = (extradata[2] >> 5) & 0x01 == 1;
+  let bit_depth = match (high_bitdepth, twelve_bit) {
+    (true, true) => 12,
+    (true, false) => 10,
+    _ => 8,
+  };
+
+  Ok(format!(
+    "av01.{}.{:02}{}.{:02}",
+    seq_profile,
+    seq_level_idx,
+    if seq_tier == 0 { 'M' } else { 'H' },
+    bit_depth,
+  ))
+}
+
+/// Produce AAC codec string `mp4a.40.A`, where `A` is the audio object
+/// type from the `AudioSpecificConfig` in the extradata. Without extradata,
+/// the audio object type is derived from the profile.
+/// 
+/// # Arguments
+/// 
+/// * `extradata` - Extradata of stream.
+/// * `profile` - Backend profile of stream, negative if unknown.
+fn codec_string_aac(extradata: &[u8], profile: i32) -> Result<String> {
+  const AUDIO_OBJECT_TYPE_ESCAPE: u8 = 31;
+
+  let audio_object_type = if !extradata.is_empty() {
+    let audio_object_type = extradata[0] >> 3;
+    if audio_object_type == AUDIO_OBJECT_TYPE_ESCAPE {
+      let extension = extradata.get(1).ok_or(Error::InvalidExtraData)?;
+      32 + (((extradata[0] & 0x07) << 3) | (extension >> 5)) as u32
+    } else {
+      audio_object_type as u32
+    }
+  } else {
+    // Backend profiles are the audio object type minus one.
+    if profile >= 0 {
+      profile as u32 + 1
+    } else {
+      return Err(Error::MissingCodecParameters);
+    }
+  };
+
+  Ok(format!("mp4a.40.{}", audio_object_type))
+}
+
+/// Get the lowest VP9 level that allows the given picture size (in luma
+/// samples), ignoring sample rate and bit rate constraints.
+fn vp9_level_for_picture_size(picture_size: u64) -> u32 {
+  const LEVELS: [(u64, u32); 9] = [
+    (36864, 10),
+    (73728, 11),
+    (122880, 20),
+    (245760, 21),
+    (552960, 30),
+    (983040, 31),
+    (2228224, 40),
+    (8912896, 50),
+    (35651584, 60),
+  ];
+
+  LEVELS
+    .iter()
+    .find(|(max_picture_size, _)| picture_size <= *max_picture_size)
+    .map(|(_, level)| *level)
+    .unwrap_or(62)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// H.264 parameter sets in Annex B format: High profile, level 3.1.
+  const EXTRADATA_H264_ANNEXB: &[u8] = &[
+    0x00, 0x00, 0x00, 0x01,
+    0x67, 0x64, 0x00, 0x1f, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0x84,
+    0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xca, 0x10,
+    0x00, 0x00, 0x00, 0x01,
+    0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0,
+  ];
+
+  /// Same H.264 parameter sets in an `avcC` record.
+  const EXTRADATA_H264_AVCC: &[u8] = &[
+    0x01, 0x64, 0x00, 0x1f, 0xff, 0xe1,
+    0x00, 0x17,
+    0x67, 0x64, 0x00, 0x1f, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0x84,
+    0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xca, 0x10,
+    0x01,
+    0x00, 0x06,
+    0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0,
+  ];
+
+  /// H.265/HEVC parameter sets in Annex B format: Main profile, main
+  /// tier, level 3.1. The profile, tier and level in the SPS contain
+  /// emulation prevention bytes.
+  const EXTRADATA_HEVC_ANNEXB: &[u8] = &[
+    0x00, 0x00, 0x00, 0x01,
+    0x40, 0x01, 0x0c, 0x01, 0xff, 0xff,
+    0x00, 0x00, 0x00, 0x01,
+    0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00,
+    0x03, 0x00, 0x00, 0x03, 0x00, 0x5d, 0xa0, 0x03, 0xc0, 0x80, 0x10, 0xe5,
+    0x96,
+    0x00, 0x00, 0x00, 0x01,
+    0x44, 0x01, 0xc1, 0x72, 0xb4, 0x62, 0x40,
+  ];
+
+  /// Header of an `hvcC` record with the same profile, tier and level,
+  /// without NAL unit arrays.
+  const EXTRADATA_HEVC_HVCC: &[u8] = &[
+    0x01, 0x01, 0x60, 0x00, 0x00, 0x00, 0xb0, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x5d, 0xf0, 0x00, 0xfc, 0xfd, 0xf8, 0xf8, 0x00, 0x00, 0x0f, 0x00,
+  ];
+
+  #[test]
+  fn h264() {
+    assert_eq!(
+      codec_string_h264(EXTRADATA_H264_ANNEXB).unwrap(),
+      "avc1.64001f");
+    assert_eq!(
+      codec_string_h264(EXTRADATA_H264_AVCC).unwrap(),
+      "avc1.64001f");
+    assert!(codec_string_h264(&[]).is_err());
+  }
+
+  #[test]
+  fn hevc() {
+    const HEV1: u32 = u32::from_le_bytes(*b"hev1");
+    assert_eq!(
+      codec_string_hevc(EXTRADATA_HEVC_HVCC, 0).unwrap(),
+      "hvc1.1.6.L93.B0");
+    assert_eq!(
+      codec_string_hevc(EXTRADATA_HEVC_ANNEXB, 0).unwrap(),
+      "hvc1.1.6.L93.B0");
+    assert_eq!(
+      codec_string_hevc(EXTRADATA_HEVC_HVCC, HEV1).unwrap(),
+      "hev1.1.6.L93.B0");
+    assert!(codec_string_hevc(&EXTRADATA_HEVC_HVCC[..8], 0).is_err());
+  }
+
+  #[test]
+  fn av1() {
+    assert_eq!(
+      codec_string_av1(&[0x81, 0x08, 0x0c, 0x00]).unwrap(),
+      "av01.0.08M.08");
+    assert_eq!(
+      codec_string_av1(&[0x81, 0x2d, 0xcc, 0x00]).unwrap(),
+      "av01.1.13H.10");
+    assert!(codec_string_av1(&[0x01, 0x08, 0x0c, 0x00]).is_err());
+    assert!(codec_string_av1(&[0x81, 0x08]).is_err());
+  }
+
+  #[test]
+  fn aac() {
+    assert_eq!(codec_string_aac(&[0x12, 0x10], -99).unwrap(), "mp4a.40.2");
+    assert_eq!(
+      codec_string_aac(&[0x2b, 0x92, 0x08, 0x00], -99).unwrap(),
+      "mp4a.40.5");
+    // Escaped audio object type.
+    assert_eq!(codec_string_aac(&[0xf9, 0x40], -99).unwrap(), "mp4a.40.42");
+    // Without extradata, the profile is used.
+    assert_eq!(codec_string_aac(&[], 1).unwrap(), "mp4a.40.2");
+    assert!(codec_string_aac(&[], -99).is_err());
+  }
+
+  #[test]
+  fn vp9_level() {
+    assert_eq!(vp9_level_for_picture_size(1280 * 720), 31);
+    assert_eq!(vp9_level_for_picture_size(1920 * 1080), 40);
+    assert_eq!(vp9_level_for_picture_size(7680 * 4320), 60);
+    assert_eq!(vp9_level_for_picture_size(16384 * 8704), 62);
+  }
+
+}
@@ -1,34 +1,51 @@
 extern crate ffmpeg_next as ffmpeg;
 
 use ffmpeg::{
+  codec::context::Context as AvCodecContext,
   codec::decoder::Video as AvDecoder,
+  codec::decoder::Audio as AvAudioDecoder,
   software::scaling::{
     context::Context as AvScaler,
     flag::Flags as AvScalerFlags,
   },
+  software::resampling::context::Context as AvResampler,
   util::{
     format::pixel::Pixel as AvPixel,
+    format::sample::Sample as AvSample,
+    channel_layout::ChannelLayout as AvChannelLayout,
     error::EAGAIN,
   },
+  Discard as AvDiscard,
   Error as AvError,
   Rational as AvRational,
 };
 
 use super::{
   Error,
+  error::Operation,
   Locator,
+  Packet,
   RawFrame,
+  RawAudioFrame,
+  StreamInfo,
+  Time,
   io::Reader,
   options::Options,
-  frame::FRAME_PIXEL_FORMAT,
+  frame::{
+    FRAME_PIXEL_FORMAT,
+    AUDIO_FRAME_SAMPLE_FORMAT,
+  },
   ffi::copy_frame_props,
 };
 
 #[cfg(feature = "ndarray")]
 use super::{
   Frame,
-  Time,
-  ffi::convert_frame_to_ndarray_rgb24,
+  AudioFrame,
+  ffi::{
+    convert_frame_to_ndarray_rgb24,
+    convert_audio_frame_to_ndarray_f32,
+  },
 };
 
 type Result<T> = std::result::Result<T, Error>;
@@ -52,6 +69,9 @@ pub struct Decoder {
   scaler: AvScaler,
   size: (u32, u32),
   frame_rate: f32,
+  keyframes_only: bool,
+  draining: bool,
+  pending_frame: Option<RawFrame>,
 }
 
 impl Decoder {
@@ -115,6 +135,31 @@ impl Decoder {
     )
   }
 
+  /// Turn the decoder into a keyframe-only version, that skips all packets
+  /// that are not keyframes and only decodes the keyframes. This is much
+  /// faster than decoding every frame, which makes it useful for producing
+  /// thumbnails or for coarse analysis of long videos. Frames keep their
+  /// original presentation timestamps.
+  /// 
+  /// Note that `seek` in this mode moves to the first keyframe at or after
+  /// the target timestamp.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// let mut decoder = Decoder::new(&PathBuf::from("video.mp4").into())
+  ///   .unwrap()
+  ///   .keyframes_only();
+  /// let (ts, keyframe) = decoder.decode().unwrap();
+  /// ```
+  pub fn keyframes_only(mut self) -> Self {
+    self.keyframes_only = true;
+    // Let the decoder itself skip anything but keyframes as well, in case
+    // packets are not flagged correctly.
+    self.decoder.skip_frame(AvDiscard::NonKey);
+    self
+  }
+
   /// Decode frames through iterator interface. This is similar to `decode`
   /// but it returns frames through an infinite iterator.
   /// 
@@ -143,7 +188,8 @@ impl Decoder {
   /// # Returns
   /// 
   /// A tuple of the frame timestamp (relative to the stream) and the
-  /// frame itself.
+  /// frame itself. The timestamp is the best effort presentation timestamp
+  /// of the frame, which can be passed to the encoder when re-encoding.
   /// 
   /// # Example
   /// 
@@ -156,9 +202,9 @@ impl Decoder {
   #[cfg(feature = "ndarray")]
   pub fn decode(&mut self) -> Result<(Time, Frame)> {
     let frame = &mut self.decode_raw()?;
-    // We use the packet DTS here (which is `frame->pkt_dts`) because that is
-    // what the encoder will use when encoding for the `PTS` field.
-    let timestamp = Time::new(Some(frame.packet().dts), self.decoder_time_base);
+    let timestamp = Time::new(
+      self.frame_timestamp(frame),
+      self.decoder_time_base);
     let frame = convert_frame_to_ndarray_rgb24(frame)
       .map_err(Error::BackendError)?;
 
@@ -176,27 +222,23 @@ impl Decoder {
   }
 
   /// Decode a single frame and return the raw ffmpeg `AvFrame`.
+  /// 
+  /// When the reader is exhausted, the decoder is drained first and the
+  /// frames it was still holding are returned. Only after that will this
+  /// function return an error for which `is_eof` holds.
   pub fn decode_raw(&mut self) -> Result<RawFrame> {
-    let mut frame: Option<RawFrame> = None;
-    while frame.is_none() {
-      let mut packet = self
-        .reader
-        .read(self.reader_stream_index)?
-        .into_inner();
-      packet.rescale_ts(self.stream_time_base(), self.decoder_time_base);
-
-      self.decoder.send_packet(&packet)
-        .map_err(Error::BackendError)?;
-
-      frame = self.decoder_receive_frame()?;
-    }
+    let frame = match self.pending_frame.take() {
+      Some(frame) => frame,
+      None => self
+        .decode_raw_unscaled()
+        .map_err(|err| self.decode_error(err))?,
+    };
 
-    let frame = frame.unwrap();
     let mut frame_scaled = RawFrame::empty();
     self
       .scaler
       .run(&frame, &mut frame_scaled)
-      .map_err(Error::BackendError)?;
+      .map_err(|err| self.decode_error(err.into()))?;
 
     copy_frame_props(&frame, &mut frame_scaled);
 
@@ -213,6 +255,52 @@ impl Decoder {
     self.frame_rate
   }
 
+  /// Seek to an exact timestamp. This seeks to the preceding key frame and
+  /// then decodes and discards frames up until the first frame with a
+  /// timestamp at or after the target timestamp. That frame is the next
+  /// frame returned by `decode` or `decode_raw`. Frame timestamps are
+  /// compared in the same way that `decode` reports them, so the timestamp
+  /// `decode` returns after seeking is never before the target.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `timestamp` - Timestamp to seek to.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// decoder.seek(&Time::from(Duration::from_secs(10))).unwrap();
+  /// let (ts, frame) = decoder.decode().unwrap();
+  /// ```
+  pub fn seek(&mut self, timestamp: &Time) -> Result<()> {
+    self.reader.seek_to_key_frame(timestamp)?;
+
+    // Throw away any state from before the seek.
+    self.decoder.flush();
+    self.draining = false;
+    self.pending_frame = None;
+
+    let target = timestamp
+      .aligned(self.decoder_time_base)
+      .into_value();
+
+    loop {
+      let frame = self
+        .decode_raw_unscaled()
+        .map_err(|err| self.decode_error(err))?;
+      let reached_target = match (self.frame_timestamp(&frame), target) {
+        (Some(frame_timestamp), Some(target)) => frame_timestamp >= target,
+        // Without timestamps, we can't do better than this.
+        _ => true,
+      };
+
+      if reached_target {
+        self.pending_frame = Some(frame);
+        return Ok(());
+      }
+    }
+  }
+
   /// Create a decoder from a `Reader` instance. Optionally provide
   /// dimensions to resize frames to.
   /// 
@@ -220,7 +308,14 @@ impl Decoder {
   /// 
   /// * `reader` - `Reader` to create decoder from.
   /// * `resize` - Optional resize strategy to apply to frames.
-  fn from_reader(
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// let reader = Reader::new_from_buf(bytes).unwrap();
+  /// let decoder = Decoder::from_reader(reader, None).unwrap();
+  /// ```
+  pub fn from_reader(
     reader: Reader,
     resize: Option<Resize>,
   ) -> Result<Self> {
@@ -272,9 +367,63 @@ impl Decoder {
       scaler,
       size,
       frame_rate,
+      keyframes_only: false,
+      draining: false,
+      pending_frame: None,
     })
   }
   
+  /// Decode a single frame without scaling it.
+  fn decode_raw_unscaled(&mut self) -> Result<RawFrame> {
+    let mut frame: Option<RawFrame> = None;
+    while frame.is_none() {
+      if !self.draining {
+        match self.reader.read(self.reader_stream_index) {
+          Ok(packet) if self.keyframes_only && !packet.is_key() => {
+            // Skip packet without passing it to the decoder.
+            continue;
+          },
+          Ok(packet) => {
+            let mut packet = packet.into_inner();
+            packet.rescale_ts(self.stream_time_base(), self.decoder_time_base);
+
+            self.decoder.send_packet(&packet)
+              .map_err(Error::BackendError)?;
+          },
+          Err(err) if err.is_eof() => {
+            // Signal end of stream to the decoder so that it will hand out
+            // the frames it is still holding on to.
+            self.decoder.send_eof()
+              .map_err(Error::BackendError)?;
+            self.draining = true;
+          },
+          Err(err) => {
+            return Err(err);
+          },
+        }
+      }
+
+      frame = self.decoder_receive_frame()?;
+      if self.draining && frame.is_none() {
+        return Err(Error::ReadExhausted);
+      }
+    }
+
+    Ok(frame.unwrap())
+  }
+
+  /// Get the timestamp of a decoded frame as reported by `decode`. This is
+  /// the best effort presentation timestamp. (The packet DTS can not be
+  /// used, since with B-frames it is behind the PTS by the reordering
+  /// delay.)
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame` - Decoded frame.
+  fn frame_timestamp(&self, frame: &RawFrame) -> Option<i64> {
+    frame.timestamp().or_else(|| frame.pts())
+  }
+
   /// Pull a decoded frame from the decoder. This function also implements
   /// retry mechanism in case the decoder signals `EAGAIN`.
   fn decoder_receive_frame(&mut self) -> Result<Option<RawFrame>> {
@@ -285,11 +434,26 @@ impl Decoder {
         => Ok(Some(frame)),
       Err(AvError::Other { errno }) if errno == EAGAIN
         => Ok(None),
+      // The decoder has been fully drained.
+      Err(AvError::Eof)
+        => Err(Error::ReadExhausted),
       Err(err)
         => Err(err.into()),
     }
   }
 
+  /// Attach context of the decoder to an error.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `err` - Error to attach context to.
+  fn decode_error(&self, err: Error) -> Error {
+    err.with_context(
+      Operation::Decode,
+      self.reader.locator(),
+      Some(self.reader_stream_index))
+  }
+
   // Acquire the time base of the input stream.
   fn stream_time_base(&self) -> AvRational {
     self
@@ -309,6 +473,11 @@ impl Drop for Decoder {
     // to drain the items still on the queue before giving up.
     const MAX_DRAIN_ITERATIONS: u32 = 100;
 
+    // If we are already draining, end of stream has been signalled.
+    if self.draining {
+      return;
+    }
+
     // We need to drain the items still in the decoders queue.
     if let Ok(()) = self.decoder.send_eof() {
       for _ in 0..MAX_DRAIN_ITERATIONS {
@@ -321,7 +490,521 @@ impl Drop for Decoder {
 
 }
 
+/// Decodes audio streams and provides the caller with decoded frames of
+/// interleaved 32-bit floating point samples.
+/// 
+/// # Example
+/// 
+/// ```
+/// let decoder = AudioDecoder::new(&PathBuf::from("video.mp4")).unwrap();
+/// decoder
+///   .decode_iter()
+///   .take_while(Result::is_ok)
+///   .for_each(|samples| println!("Got samples!"));
+/// ```
+pub struct AudioDecoder {
+  reader: Reader,
+  reader_stream_index: usize,
+  decoder: AvAudioDecoder,
+  decoder_time_base: AvRational,
+  decoder_channel_layout: AvChannelLayout,
+  resampler: AvResampler,
+  sample_rate: u32,
+  channels: u16,
+  draining: bool,
+}
+
+impl AudioDecoder {
+
+  /// Create a new audio decoder for the specified file.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `source` - Locator to file to decode.
+  pub fn new(
+    source: &Locator,
+  ) -> Result<Self> {
+    Self::from_reader(Reader::new(source)?)
+  }
+
+  /// Create a new audio decoder for the specified file with input options.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `source` - Locator to file to decode.
+  /// * `options` - The input options.
+  pub fn new_with_options(
+    source: &Locator,
+    options: &Options,
+  ) -> Result<Self> {
+    Self::from_reader(Reader::new_with_options(source, options)?)
+  }
+
+  /// Decode audio frames through iterator interface. This is similar to
+  /// `decode` but it returns frames through an infinite iterator.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// decoder
+  ///   .decode_iter()
+  ///   .take_while(Result::is_ok)
+  ///   .map(Result::unwrap)
+  ///   .for_each(|(ts, samples)| {
+  ///     // Do something with samples...
+  ///   });
+  /// ```
+  #[cfg(feature = "ndarray")]
+  pub fn decode_iter(
+    &mut self,
+  ) -> impl Iterator<Item=Result<(Time, AudioFrame)>> + '_ {
+    std::iter::from_fn(move || {
+      Some(self.decode())
+    })
+  }
+
+  /// Decode a single audio frame.
+  /// 
+  /// # Returns
+  /// 
+  /// A tuple of the frame timestamp (relative to the stream) and the
+  /// samples in the frame as an array with dimensions `(S, C)`, where
+  /// `S` is the number of samples and `C` the number of channels.
+  #[cfg(feature = "ndarray")]
+  pub fn decode(&mut self) -> Result<(Time, AudioFrame)> {
+    let frame = self.decode_raw()?;
+    let timestamp = Time::new(frame.pts(), self.decoder_time_base);
+    let frame = convert_audio_frame_to_ndarray_f32(&frame)
+      .map_err(Error::BackendError)?;
+
+    Ok((timestamp, frame))
+  }
+
+  /// Decode audio frames through iterator interface. This is similar to
+  /// `decode_raw` but it returns frames through an infinite iterator.
+  pub fn decode_raw_iter(
+    &mut self,
+  ) -> impl Iterator<Item=Result<RawAudioFrame>> + '_ {
+    std::iter::from_fn(move || {
+      Some(self.decode_raw())
+    })
+  }
+
+  /// Decode a single audio frame and return the raw ffmpeg `AvAudioFrame`.
+  /// The samples are interleaved and in 32-bit floating point format.
+  /// 
+  /// When the reader is exhausted, the decoder is drained first and the
+  /// frames it was still holding are returned. Only after that will this
+  /// function return an error for which `is_eof` holds.
+  pub fn decode_raw(&mut self) -> Result<RawAudioFrame> {
+    self
+      .decode_raw_resampled()
+      .map_err(|err| self.decode_error(err))
+  }
+
+  /// Get the decoders input sample rate.
+  pub fn sample_rate(&self) -> u32 {
+    self.sample_rate
+  }
+
+  /// Get the decoders number of input channels.
+  pub fn channels(&self) -> u16 {
+    self.channels
+  }
+
+  /// Decode a single audio frame and resample it to interleaved 32-bit
+  /// floating point format.
+  fn decode_raw_resampled(&mut self) -> Result<RawAudioFrame> {
+    let mut frame: Option<RawAudioFrame> = None;
+    while frame.is_none() {
+      if !self.draining {
+        match self.reader.read(self.reader_stream_index) {
+          Ok(packet) => {
+            let mut packet = packet.into_inner();
+            packet.rescale_ts(self.stream_time_base(), self.decoder_time_base);
+
+            self.decoder.send_packet(&packet)
+              .map_err(Error::BackendError)?;
+          },
+          Err(err) if err.is_eof() => {
+            // Signal end of stream to the decoder so that it will hand out
+            // the frames it is still holding on to.
+            self.decoder.send_eof()
+              .map_err(Error::BackendError)?;
+            self.draining = true;
+          },
+          Err(err) => {
+            return Err(err);
+          },
+        }
+      }
+
+      frame = self.decoder_receive_frame()?;
+      if self.draining && frame.is_none() {
+        return Err(Error::ReadExhausted);
+      }
+    }
+
+    let mut frame = frame.unwrap();
+    // Some decoders do not fill in the channel layout, in which case the
+    // resampler would reject the frame.
+    if frame.channel_layout().is_empty() {
+      frame.set_channel_layout(self.decoder_channel_layout);
+    }
+
+    let mut frame_resampled = RawAudioFrame::empty();
+    self
+      .resampler
+      .run(&frame, &mut frame_resampled)
+      .map_err(Error::BackendError)?;
+    // Copy over PTS from old frame.
+    frame_resampled.set_pts(frame.pts());
+
+    Ok(frame_resampled)
+  }
+
+  /// Create an audio decoder from a `Reader` instance.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `reader` - `Reader` to create decoder from.
+  pub fn from_reader(
+    reader: Reader,
+  ) -> Result<Self> {
+    let reader_stream_index = reader.best_audio_stream_index()?;
+    let reader_stream = reader
+      .input
+      .stream(reader_stream_index)
+      .ok_or(AvError::StreamNotFound)?;
+
+    let codec = reader_stream.codec().unwrap();
+    let decoder = codec
+      .decoder()
+      .audio()?;
+    let decoder_time_base = decoder.time_base();
+
+    if decoder.format() == AvSample::None ||
+       decoder.rate() == 0 || decoder.channels() == 0 {
+      return Err(Error::MissingCodecParameters);
+    }
+
+    let decoder_channel_layout = if decoder.channel_layout().is_empty() {
+      AvChannelLayout::default(decoder.channels() as i32)
+    } else {
+      decoder.channel_layout()
+    };
+
+    // Only the sample format is converted. Sample rate and channel layout
+    // are retained, which means the resampler never buffers samples.
+    let resampler = AvResampler::get(
+      decoder.format(),
+      decoder_channel_layout,
+      decoder.rate(),
+      AUDIO_FRAME_SAMPLE_FORMAT,
+      decoder_channel_layout,
+      decoder.rate())?;
+
+    let sample_rate = decoder.rate();
+    let channels = decoder.channels();
+
+    Ok(Self {
+      reader,
+      reader_stream_index,
+      decoder,
+      decoder_time_base,
+      decoder_channel_layout,
+      resampler,
+      sample_rate,
+      channels,
+      draining: false,
+    })
+  }
+
+  /// Pull a decoded frame from the decoder. This function also implements
+  /// retry mechanism in case the decoder signals `EAGAIN`.
+  fn decoder_receive_frame(&mut self) -> Result<Option<RawAudioFrame>> {
+    let mut frame = RawAudioFrame::empty();
+    let decode_result = self.decoder.receive_frame(&mut frame);
+    match decode_result {
+      Ok(())
+        => Ok(Some(frame)),
+      Err(AvError::Other { errno }) if errno == EAGAIN
+        => Ok(None),
+      // The decoder has been fully drained.
+      Err(AvError::Eof)
+        => Err(Error::ReadExhausted),
+      Err(err)
+        => Err(err.into()),
+    }
+  }
+
+  /// Attach context of the decoder to an error.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `err` - Error to attach context to.
+  fn decode_error(&self, err: Error) -> Error {
+    err.with_context(
+      Operation::Decode,
+      self.reader.locator(),
+      Some(self.reader_stream_index))
+  }
+
+  // Acquire the time base of the input stream.
+  fn stream_time_base(&self) -> AvRational {
+    self
+      .reader
+      .input
+      .stream(self.reader_stream_index)
+      .unwrap()
+      .time_base()
+  }
+
+}
+
+impl Drop for AudioDecoder {
+
+  fn drop(&mut self) {
+    // Maximum number of invocations to `decoder_receive_frame`
+    // to drain the items still on the queue before giving up.
+    const MAX_DRAIN_ITERATIONS: u32 = 100;
+
+    // If we are already draining, end of stream has been signalled.
+    if self.draining {
+      return;
+    }
+
+    // We need to drain the items still in the decoders queue.
+    if let Ok(()) = self.decoder.send_eof() {
+      for _ in 0..MAX_DRAIN_ITERATIONS {
+        if self.decoder_receive_frame().is_err() {
+          break;
+        }
+      }
+    }
+  }
+
+}
+
+/// Decodes packets that are pushed into it by the caller, as opposed to
+/// `Decoder` which pulls packets from its own `Reader`. This is useful for
+/// decoding packets that come from elsewhere, for example from an RTP
+/// depacketizer, or packets that are also being muxed.
+/// 
+/// # Example
+/// 
+/// ```
+/// let mut reader = Reader::new(&PathBuf::from("video.mp4").into()).unwrap();
+/// let stream_index = reader.best_video_stream_index().unwrap();
+/// let mut decoder = PacketDecoder::new(
+///     reader.stream_info(stream_index).unwrap())
+///   .unwrap();
+/// 
+/// while let Ok(packet) = reader.read(stream_index) {
+///   for frame in decoder.decode_raw(packet).unwrap() {
+///     // Do something with frame...
+///   }
+/// }
+/// 
+/// for frame in decoder.drain_raw().unwrap() {
+///   // Do something with remaining frames...
+/// }
+/// ```
+pub struct PacketDecoder {
+  stream_index: usize,
+  decoder: AvDecoder,
+  #[cfg(feature = "ndarray")]
+  time_base: AvRational,
+  scaler: AvScaler,
+  size: (u32, u32),
+}
+
+impl PacketDecoder {
+
+  /// Create a new packet decoder for the stream described by the stream
+  /// information.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `stream_info` - Information of the stream the packets belong to.
+  pub fn new(
+    stream_info: StreamInfo,
+  ) -> Result<Self> {
+    Self::new_with_resize(stream_info, None)
+  }
+
+  /// Create a new packet decoder for the stream described by the stream
+  /// information. Optionally provide dimensions to resize frames to.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `stream_info` - Information of the stream the packets belong to.
+  /// * `resize` - Optional resize strategy to apply to frames.
+  pub fn new_with_resize(
+    stream_info: StreamInfo,
+    resize: Option<Resize>,
+  ) -> Result<Self> {
+    #[allow(unused_variables)]
+    let (stream_index, codec_parameters, time_base) = stream_info.into_parts();
+
+    let decoder = AvCodecContext::from_parameters(codec_parameters)?
+      .decoder()
+      .video()?;
+
+    if decoder.format() == AvPixel::None ||
+       decoder.width() == 0 || decoder.height() == 0 {
+      return Err(Error::MissingCodecParameters);
+    }
+
+    let (resize_width, resize_height) = resize
+      .map(|resize| match resize {
+        Resize::Exact(w, h) => (w, h),
+        Resize::Fit(w, h) => calculate_fit_dims(
+          (decoder.width(), decoder.height()),
+          (w, h)),
+      })
+      .unwrap_or((decoder.width(), decoder.height()));
+
+    let scaler = AvScaler::get(
+      decoder.format(),
+      decoder.width(),
+      decoder.height(),
+      FRAME_PIXEL_FORMAT,
+      resize_width,
+      resize_height,
+      AvScalerFlags::AREA)?;
+
+    let size = (decoder.width(), decoder.height());
+
+    Ok(Self {
+      stream_index,
+      decoder,
+      #[cfg(feature = "ndarray")]
+      time_base,
+      scaler,
+      size,
+    })
+  }
+
+  /// Decode a single packet and return the decoded frames, if any.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `packet` - Packet to decode.
+  /// 
+  /// # Returns
+  /// 
+  /// Zero or more tuples of frame timestamp (relative to the stream) and
+  /// the frame itself.
+  #[cfg(feature = "ndarray")]
+  pub fn decode(&mut self, packet: Packet) -> Result<Vec<(Time, Frame)>> {
+    self
+      .decode_raw(packet)?
+      .into_iter()
+      .map(|frame| self.convert_to_ndarray(frame))
+      .collect()
+  }
+
+  /// Signal end of stream to the decoder and return the frames that were
+  /// still buffered inside of it. After draining, the decoder is reset
+  /// and can be used for new packets.
+  #[cfg(feature = "ndarray")]
+  pub fn drain(&mut self) -> Result<Vec<(Time, Frame)>> {
+    self
+      .drain_raw()?
+      .into_iter()
+      .map(|frame| self.convert_to_ndarray(frame))
+      .collect()
+  }
+
+  /// Decode a single packet and return the decoded raw ffmpeg frames, if
+  /// any. Timestamps of the frames are in the time base of the stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `packet` - Packet to decode.
+  pub fn decode_raw(&mut self, packet: Packet) -> Result<Vec<RawFrame>> {
+    let packet = packet.into_inner();
+    self.decoder.send_packet(&packet)
+      .map_err(|err| self.decode_error(err.into()))?;
+
+    self
+      .receive_frames()
+      .map_err(|err| self.decode_error(err))
+  }
+
+  /// Signal end of stream to the decoder and return the raw frames that
+  /// were still buffered inside of it. After draining, the decoder is reset
+  /// and can be used for new packets.
+  pub fn drain_raw(&mut self) -> Result<Vec<RawFrame>> {
+    self.decoder.send_eof()
+      .map_err(|err| self.decode_error(err.into()))?;
+
+    let frames = self
+      .receive_frames()
+      .map_err(|err| self.decode_error(err))?;
+    // Reset the decoder so it will accept new packets.
+    self.decoder.flush();
+
+    Ok(frames)
+  }
+
+  /// Get the decoders input size (resolution dimensions): width and height.
+  pub fn size(&self) -> (u32, u32) {
+    self.size
+  }
+
+  /// Attach context of the decoder to an error.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `err` - Error to attach context to.
+  fn decode_error(&self, err: Error) -> Error {
+    err.with_context(Operation::Decode, None, Some(self.stream_index))
+  }
+
+  /// Pull all available frames from the decoder and rescale them.
+  fn receive_frames(&mut self) -> Result<Vec<RawFrame>> {
+    let mut frames = Vec::new();
+    loop {
+      let mut frame = RawFrame::empty();
+      match self.decoder.receive_frame(&mut frame) {
+        Ok(()) => {
+          let mut frame_scaled = RawFrame::empty();
+          self
+            .scaler
+            .run(&frame, &mut frame_scaled)
+            .map_err(Error::BackendError)?;
+
+          copy_frame_props(&frame, &mut frame_scaled);
+          frames.push(frame_scaled);
+        },
+        Err(AvError::Other { errno }) if errno == EAGAIN
+          => break,
+        Err(AvError::Eof)
+          => break,
+        Err(err)
+          => return Err(err.into()),
+      }
+    }
+
+    Ok(frames)
+  }
+
+  /// Convert raw frame to `ndarray` frame with timestamp.
+  #[cfg(feature = "ndarray")]
+  fn convert_to_ndarray(&self, mut frame: RawFrame) -> Result<(Time, Frame)> {
+    let timestamp = Time::new(Some(frame.packet().dts), self.time_base);
+    let frame = convert_frame_to_ndarray_rgb24(&mut frame)
+      .map_err(Error::BackendError)?;
+
+    Ok((timestamp, frame))
+  }
+
+}
+
 /// Represents the possible resize strategies.
+#[derive(Clone, Copy, Debug)]
 pub enum Resize {
   /// When resizing with `Resize::Exact`, each frame will be
   /// resized to the exact width and height given, without
@@ -361,3 +1044,72 @@ fn calculate_fit_dims(
       (h as f32 * f) as u32)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::path::Path;
+
+  use crate::{
+    Encoder,
+    EncoderSettings,
+  };
+
+  const WIDTH: u32 = 64;
+  const HEIGHT: u32 = 64;
+
+  /// Encode a short H.264 stream with B-frames to a temporary file.
+  fn encode_with_b_frames(path: &Path, num_frames: i64) {
+    let settings = EncoderSettings::for_h264_yuv420p(
+        WIDTH as usize,
+        HEIGHT as usize,
+        false)
+      .with_frame_rate(25, 1)
+      .with_max_b_frames(2);
+    let mut encoder = Encoder::new(&path.to_path_buf().into(), settings).unwrap();
+    for i in 0..num_frames {
+      let mut frame = RawFrame::new(FRAME_PIXEL_FORMAT, WIDTH, HEIGHT);
+      let stride = frame.stride(0);
+      // Moving gradient, so that frames are predicted from each other.
+      for (y, row) in frame.data_mut(0).chunks_mut(stride).enumerate() {
+        for (x, value) in row.iter_mut().enumerate() {
+          *value = (x as i64 + y as i64 + i * 4) as u8;
+        }
+      }
+      frame.set_pts(Some(i));
+      encoder.encode_raw(frame).unwrap();
+    }
+    encoder.finish().unwrap();
+  }
+
+  #[test]
+  fn seek_b_frames_to_exact_presentation_timestamp() {
+    crate::init();
+
+    let path = std::env::temp_dir().join("video-rs-test-seek-b-frames.mp4");
+    encode_with_b_frames(&path, 48);
+
+    let mut decoder = Decoder::new(&path.clone().into()).unwrap();
+    let mut timestamps = Vec::new();
+    while let Ok(frame) = decoder.decode_raw() {
+      timestamps.push(decoder.frame_timestamp(&frame).unwrap());
+    }
+    assert!(decoder.decoder.has_b_frames());
+    assert_eq!(timestamps.len(), 48);
+
+    // Seek to frames that are not keyframes, so that the seek has to
+    // decode past reordered frames.
+    for index in [13, 22, 35] {
+      let target = Time::new(
+        Some(timestamps[index]),
+        decoder.decoder_time_base);
+      decoder.seek(&target).unwrap();
+      let frame = decoder.decode_raw().unwrap();
+      assert_eq!(decoder.frame_timestamp(&frame), Some(timestamps[index]));
+    }
+
+    let _ = std::fs::remove_file(path);
+  }
+
+}
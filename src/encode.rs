@@ -5,16 +5,25 @@ use ffmpeg::{
   codec::{
     Id as AvCodecId,
     codec::Codec as AvCodec,
+    context::Context as AvCodecContext,
     packet::Packet as AvPacket,
     encoder::video::Video as AvEncoder,
+    encoder::video::Encoder as AvOpenedEncoder,
+    encoder::audio::Audio as AvAudioEncoder,
     flag::Flags as AvCodecFlags,
   },
   software::scaling::{
     context::Context as AvScaler,
     flag::Flags as AvScalerFlags,
   },
+  software::resampling::context::Context as AvResampler,
   util::{
     format::Pixel as AvPixel,
+    format::sample::{
+      Sample as AvSample,
+      Type as AvSampleType,
+    },
+    channel_layout::ChannelLayout as AvChannelLayout,
     picture::Type as AvFrameType,
     mathematics::rescale::TIME_BASE,
     error::EAGAIN,
@@ -26,22 +35,38 @@ use ffmpeg::{
 
 use super::{
   Error,
+  error::Operation,
   Locator,
+  Packet,
   RawFrame,
+  RawAudioFrame,
+  StreamInfo,
+  Timecode,
   io::{
     Writer,
     private::Write,
   },
   options::Options,
   frame::FRAME_PIXEL_FORMAT,
-  ffi::get_encoder_time_base,
+  ffi::{
+    get_encoder_time_base,
+    get_encoder_fixed_quality,
+    get_resampler_out_samples,
+    set_frame_quality,
+    set_output_metadata,
+    AudioFifo,
+  },
 };
 
 #[cfg(feature = "ndarray")]
 use super::{
   Frame,
+  AudioFrame,
   Time,
-  ffi::convert_ndarray_to_frame_rgb24,
+  ffi::{
+    convert_ndarray_to_frame_rgb24,
+    convert_ndarray_to_audio_frame_f32,
+  },
 };
 
 type Result<T> = std::result::Result<T, Error>;
@@ -72,13 +97,15 @@ pub struct Encoder {
   scaler: AvScaler,
   scaler_width: u32,
   scaler_height: u32,
+  keyframe_interval: u64,
+  fixed_quality: Option<i32>,
   frame_count: u64,
+  audio: Option<AudioStream>,
   have_written_header: bool,
   have_finished: bool,
 }
 
 impl Encoder {
-  const KEY_FRAME_INTERVAL: u64 = 12;
 
   /// Create a new encoder that writes to the specified file.
   /// 
@@ -162,6 +189,109 @@ impl Encoder {
     self
   }
 
+  /// Add an audio stream to the output. Audio samples can then be encoded
+  /// with `encode_audio` or `encode_audio_raw` alongside the video frames.
+  /// 
+  /// Note: This must be called before encoding the first frame, and at most
+  /// once. Otherwise, `Error::InvalidEncoderSettings` is returned.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `settings` - Audio encoder settings to use.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// let encoder = Encoder::new(
+  ///     &PathBuf::from("video_out.mp4").into(),
+  ///     Settings::for_h264_yuv420p(800, 600, false))
+  ///   .unwrap()
+  ///   .with_audio(AudioSettings::for_aac(48000, 2))
+  ///   .unwrap();
+  /// ```
+  pub fn with_audio(
+    mut self,
+    settings: AudioSettings,
+  ) -> Result<Self> {
+    // Streams can not be added after the header has been written.
+    if self.have_written_header {
+      return Err(Error::InvalidEncoderSettings(
+        "audio stream must be added before encoding the first frame"));
+    }
+    if self.audio.is_some() {
+      return Err(Error::InvalidEncoderSettings(
+        "encoder already has an audio stream"));
+    }
+
+    let global_header = self
+      .writer
+      .output
+      .format()
+      .flags()
+      .contains(AvFormatFlags::GLOBAL_HEADER);
+
+    let mut writer_stream = self
+      .writer
+      .output
+      .add_stream(settings.codec())?;
+    let writer_stream_index = writer_stream.index();
+
+    let mut encoder = Self::audio_encoder(&writer_stream)?;
+    // Some formats require this flag to be set or the output will
+    // not be playable by dumb players.
+    if global_header {
+      encoder.set_flags(AvCodecFlags::GLOBAL_HEADER);
+    }
+
+    let mut encoder = settings.apply_to(encoder);
+    // Audio is timed per sample, so the natural time base is one over
+    // the sample rate.
+    let encoder_time_base = AvRational::new(1, settings.sample_rate as i32);
+    encoder.set_time_base(encoder_time_base);
+
+    let frame_size = encoder
+      .open_with(settings.options().to_dict())?
+      .frame_size() as usize;
+
+    let encoder = Self::audio_encoder(&writer_stream)?;
+    writer_stream.set_parameters(encoder);
+
+    let encoder = Self::audio_encoder(&writer_stream)?;
+    let fifo = AudioFifo::new(encoder.format(), encoder.channels())?;
+
+    self.audio = Some(AudioStream {
+      writer_stream_index,
+      encoder,
+      encoder_time_base,
+      resampler: None,
+      fifo,
+      frame_size,
+      next_pts: None,
+    });
+
+    Ok(self)
+  }
+
+  /// Write a start timecode into the output. Containers that support
+  /// timecodes (like MOV, MP4 and MXF) store it with the video stream.
+  /// 
+  /// Note: This must be called before encoding the first frame.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `timecode` - Timecode of the first frame.
+  pub fn with_timecode(
+    mut self,
+    timecode: &Timecode,
+  ) -> Result<Self> {
+    set_output_metadata(
+      &mut self.writer.output,
+      Timecode::TAG,
+      &timecode.to_string())?;
+
+    Ok(self)
+  }
+
   /// Encode a single `ndarray` frame.
   /// 
   /// # Arguments
@@ -193,30 +323,137 @@ impl Encoder {
     self.encode_raw(frame)
   }
 
+  /// Encode audio samples in `ndarray` form.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `samples` - Interleaved samples to encode in `(S, C)` format and
+  ///   standard layout, at the sample rate of the audio stream.
+  /// * `source_timestamp` - Timestamp of the first sample in the original
+  ///   source.
+  #[cfg(feature = "ndarray")]
+  pub fn encode_audio(
+    &mut self,
+    samples: &AudioFrame,
+    source_timestamp: &Time,
+  ) -> Result<()> {
+    let audio = self
+      .audio
+      .as_ref()
+      .ok_or(AvError::StreamNotFound)?;
+
+    let mut frame = convert_ndarray_to_audio_frame_f32(
+        samples,
+        audio.encoder.rate())
+      .map_err(Error::BackendError)?;
+
+    frame.set_pts(
+      source_timestamp
+        .aligned(audio.encoder_time_base)
+        .into_value());
+
+    self.encode_audio_raw(frame)
+  }
+
+  /// Encode a single raw audio frame. The frame may have any sample format
+  /// (interleaved or planar), channel layout and sample rate, it will be
+  /// resampled to what the audio codec requires and cut into frames of the
+  /// size the codec expects.
+  /// 
+  /// The PTS of the first frame, if set, determines the start time of the
+  /// audio stream and must be expressed in samples (the time base is one
+  /// over the sample rate of the audio stream).
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame` - Audio frame to encode.
+  pub fn encode_audio_raw(&mut self, frame: RawAudioFrame) -> Result<()> {
+    let stream_index = self
+      .audio
+      .as_ref()
+      .map(|audio| audio.writer_stream_index);
+
+    self
+      .encode_audio_frame(frame)
+      .map_err(|err| self.encode_error(err, stream_index))
+  }
+
   /// Encode a single raw frame.
   /// 
   /// # Arguments
   /// 
   /// * `frame` - Frame to encode.
   pub fn encode_raw(&mut self, frame: RawFrame) -> Result<()> {
+    self
+      .encode_video_frame(frame)
+      .map_err(|err| self.encode_error(err, Some(self.writer_stream_index)))
+  }
+
+  /// Signal to the encoder that writing has finished. This will cause any
+  /// packets in the encoder to be flushed and a trailer to be written if
+  /// the container format has one.
+  /// 
+  /// Note: If you don't call this function before dropping the encoder, it
+  /// will be called automatically. This will block the caller thread. Any
+  /// errors cannot be propagated in this case.
+  pub fn finish(&mut self) -> Result<()> {
+    if !self.have_finished {
+      self.have_finished = true;
+      self
+        .finish_streams()
+        .map_err(|err| self.encode_error(err, None))?;
+    }
+
+    Ok(())
+  }
+
+  /// Encode a single raw audio frame into the audio stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame` - Audio frame to encode.
+  fn encode_audio_frame(&mut self, frame: RawAudioFrame) -> Result<()> {
+    self.write_header_if_needed()?;
+
+    let audio = self
+      .audio
+      .as_mut()
+      .ok_or(AvError::StreamNotFound)?;
+    let writer_stream_index = audio.writer_stream_index;
+    let encoder_time_base = audio.encoder_time_base;
+
+    let packets = audio.encode(frame)?;
+    for packet in packets {
+      self.write_to_stream(packet, writer_stream_index, encoder_time_base)?;
+    }
+
+    Ok(())
+  }
+
+  /// Encode a single raw frame into the video stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame` - Frame to encode.
+  fn encode_video_frame(&mut self, frame: RawFrame) -> Result<()> {
     if frame.width() != self.scaler_width ||
        frame.height() != self.scaler_height ||
        frame.format() != FRAME_PIXEL_FORMAT {
       return Err(Error::InvalidFrameFormat);
     }
 
-    // Write file header if we hadn't done that yet.
-    if !self.have_written_header {
-      let _ = self.writer.write_header()?;
-      self.have_written_header = true;
-    }
+    self.write_header_if_needed()?;
 
     // Reformat frame to target pixel format.
     let mut frame = self.scale(frame)?;
     // Producer key frame every once in a while
-    if self.frame_count % Self::KEY_FRAME_INTERVAL == 0 {
+    if self.frame_count % self.keyframe_interval == 0 {
       frame.set_kind(AvFrameType::I);
     }
+    // Encoders with a fixed quality scale take the quality from the frame.
+    if let Some(quality) = self.fixed_quality {
+      set_frame_quality(&mut frame, quality);
+    }
 
     self
       .encoder
@@ -230,21 +467,21 @@ impl Encoder {
     Ok(())
   }
 
-  /// Signal to the encoder that writing has finished. This will cause any
-  /// packets in the encoder to be flushed and a trailer to be written if
-  /// the container format has one.
-  /// 
-  /// Note: If you don't call this function before dropping the encoder, it
-  /// will be called automatically. This will block the caller thread. Any
-  /// errors cannot be propagated in this case.
-  pub fn finish(&mut self) -> Result<()> {
-    if !self.have_finished {
-      self.have_finished = true;
-      self.flush()?;
-      self.writer.write_trailer()?;
-    }
+  /// Flush all streams and write the trailer.
+  fn finish_streams(&mut self) -> Result<()> {
+    self.flush()?;
+    self.flush_audio()?;
+    self.writer.write_trailer()
+  }
 
-    Ok(())
+  /// Attach context of the encoder to an error.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `err` - Error to attach context to.
+  /// * `stream_index` - Index of output stream, if applicable.
+  fn encode_error(&self, err: Error, stream_index: Option<usize>) -> Error {
+    err.with_context(Operation::Encode, Some(&self.writer.dest), stream_index)
   }
 
   /// Create an encoder from a `FileWriter` instance.
@@ -257,6 +494,8 @@ impl Encoder {
     mut writer: Writer,
     settings: Settings,
   ) -> Result<Self> {
+    settings.validate()?;
+
     let global_header = writer
       .output
       .format()
@@ -265,7 +504,7 @@ impl Encoder {
 
     let mut writer_stream = writer
       .output
-      .add_stream(settings.codec())?;
+      .add_stream(settings.codec()?)?;
     let writer_stream_index = writer_stream.index();
 
     let mut encoder = Self::encoder(&writer_stream)?;
@@ -309,12 +548,25 @@ impl Encoder {
       scaler,
       scaler_width,
       scaler_height,
+      keyframe_interval: settings.keyframe_interval(),
+      fixed_quality: get_encoder_fixed_quality(&encoder),
       frame_count: 0,
+      audio: None,
       have_written_header: false,
       have_finished: false,
     })
   }
 
+  /// Write file header if we hadn't done that yet.
+  fn write_header_if_needed(&mut self) -> Result<()> {
+    if !self.have_written_header {
+      let _ = self.writer.write_header()?;
+      self.have_written_header = true;
+    }
+
+    Ok(())
+  }
+
   /// Apply scaling (or pixel reformatting in this case) on the frame with the
   /// scaler we initialized earlier.
   /// 
@@ -366,12 +618,33 @@ impl Encoder {
       .map_err(Error::BackendError)
   }
 
-  /// Acquire the time base of the output stream.
-  fn stream_time_base(&mut self) -> AvRational {
+  /// Helper function to extract audio encoder from stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `writer_stream` - Stream to get encoder of.
+  /// 
+  /// # Returns
+  /// 
+  /// Raw ffmpeg audio encoder belonging to given stream.
+  fn audio_encoder(writer_stream: &StreamMut) -> Result<AvAudioEncoder> {
+    writer_stream
+      .codec().unwrap()
+      .encoder()
+      .audio()
+      .map_err(Error::BackendError)
+  }
+
+  /// Acquire the time base of an output stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `stream_index` - Index of output stream.
+  fn stream_time_base(&mut self, stream_index: usize) -> AvRational {
     self
       .writer
       .output
-      .stream(self.writer_stream_index)
+      .stream(stream_index)
       .unwrap()
       .time_base()
   }
@@ -381,18 +654,41 @@ impl Encoder {
   /// # Arguments
   /// 
   /// * `packet` - Encoded packet.
-  fn write(&mut self, mut packet: AvPacket) -> Result<()> {
-    packet.set_stream(self.writer_stream_index);
+  fn write(&mut self, packet: AvPacket) -> Result<()> {
+    self.write_to_stream(
+      packet,
+      self.writer_stream_index,
+      self.encoder_time_base,
+    )?;
+
+    self.frame_count += 1;
+    Ok(())
+  }
+
+  /// Write encoded packet to a specific output stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `packet` - Encoded packet.
+  /// * `stream_index` - Index of output stream.
+  /// * `encoder_time_base` - Time base of the encoder that produced the
+  ///   packet.
+  fn write_to_stream(
+    &mut self,
+    mut packet: AvPacket,
+    stream_index: usize,
+    encoder_time_base: AvRational,
+  ) -> Result<()> {
+    packet.set_stream(stream_index);
     packet.set_position(-1);
-    packet.rescale_ts(self.encoder_time_base, self.stream_time_base());
+    packet.rescale_ts(encoder_time_base, self.stream_time_base(stream_index));
     let _ =
       if self.interleaved {
         self.writer.write_interleaved(&mut packet)?;
       } else {
         self.writer.write(&mut packet)?;
       };
-    
-    self.frame_count += 1;
+
     Ok(())
   }
 
@@ -420,6 +716,22 @@ impl Encoder {
     Ok(())
   }
 
+  /// Flush the audio encoder if there is one, including any samples still
+  /// waiting to be encoded.
+  fn flush_audio(&mut self) -> Result<()> {
+    if let Some(audio) = self.audio.as_mut() {
+      let writer_stream_index = audio.writer_stream_index;
+      let encoder_time_base = audio.encoder_time_base;
+
+      let packets = audio.flush()?;
+      for packet in packets {
+        self.write_to_stream(packet, writer_stream_index, encoder_time_base)?;
+      }
+    }
+
+    Ok(())
+  }
+
 }
 
 impl Drop for Encoder {
@@ -430,47 +742,690 @@ impl Drop for Encoder {
 
 }
 
-/// Holds a logical combination of encoder settings.
-pub struct Settings<'o> {
-  width: u32,
-  height: u32,
-  pixel_format: AvPixel,
-  options: Options<'o>,
+/// Encodes frames into packets in memory instead of writing them to a file.
+/// The packets can be passed on to any muxer, such as `BufMuxer`,
+/// `PacketizedBufMuxer` or `RtpMuxer`, or to another transport.
+/// 
+/// The encoder produces global headers (the parameter sets are stored in
+/// the stream extradata), as is required by most muxers.
+/// 
+/// # Example
+/// 
+/// ```
+/// let mut encoder = PacketEncoder::new(
+///     Settings::for_h264_yuv420p(800, 600, true))
+///   .unwrap();
+/// let mut muxer = RtpMuxer::new()
+///   .unwrap()
+///   .with_stream(encoder.stream_info())
+///   .unwrap();
+/// 
+/// for packet in encoder.encode(&frame, &timestamp).unwrap() {
+///   let rtp_bufs = muxer.mux(packet).unwrap();
+///   // Send RTP buffers...
+/// }
+/// ```
+pub struct PacketEncoder {
+  encoder: AvOpenedEncoder,
+  encoder_time_base: AvRational,
+  scaler: AvScaler,
+  scaler_width: u32,
+  scaler_height: u32,
+  keyframe_interval: u64,
+  fixed_quality: Option<i32>,
+  frame_count: u64,
 }
 
-impl<'o> Settings<'o> {
-  /// This is the assumed FPS for the encoder to use. Note that this does not
-  /// need to be correct exactly.
-  const FRAME_RATE: i32 = 30;
+impl PacketEncoder {
+  /// Stream index that is used for the packets produced by the encoder.
+  const STREAM_INDEX: usize = 0;
 
-  /// Create encoder settings for an H264 stream with YUV420p pixel format.
-  /// This will encode to arguably the most widely compatible video file since
-  /// H264 is a common codec and YUV420p is the most commonly used pixel format.
-  pub fn for_h264_yuv420p(
-    width: usize,
-    height: usize,
-    realtime: bool,
-  ) -> Settings<'o> {
-    let options = if realtime {
-      Options::new_h264_realtime()
-    } else {
-      Options::new_h264()
-    };
+  /// Create a new encoder that produces packets.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `settings` - Encoder settings to use.
+  pub fn new(
+    settings: Settings,
+  ) -> Result<Self> {
+    settings.validate()?;
 
-    Self {
-      width: width as u32,
-      height: height as u32,
-      pixel_format: AvPixel::YUV420P,
-      options,
-    }
+    let codec = settings.codec()?;
+
+    let mut encoder = AvCodecContext::new()
+      .encoder()
+      .video()?;
+    // There is no container to tell us whether it needs global headers,
+    // so always produce them. Muxers need the extradata to write headers
+    // or SDP.
+    encoder.set_flags(AvCodecFlags::GLOBAL_HEADER);
+
+    let mut encoder = settings.apply_to(encoder);
+    // Just use the ffmpeg global time base which is precise enough
+    // that we should never get in trouble
+    encoder.set_time_base(TIME_BASE);
+
+    let encoder = encoder
+      .open_as_with(codec, settings.options().to_dict())?;
+    let encoder_time_base = get_encoder_time_base(&encoder);
+
+    let scaler_width = encoder.width();
+    let scaler_height = encoder.height();
+    let scaler = AvScaler::get(
+      FRAME_PIXEL_FORMAT,
+      scaler_width,
+      scaler_height,
+      encoder.format(),
+      scaler_width,
+      scaler_height,
+      AvScalerFlags::empty())?;
+
+    Ok(Self {
+      encoder,
+      encoder_time_base,
+      scaler,
+      scaler_width,
+      scaler_height,
+      keyframe_interval: settings.keyframe_interval(),
+      fixed_quality: get_encoder_fixed_quality(&encoder),
+      frame_count: 0,
+    })
   }
 
-  /// Apply the settings to an encoder.
+  /// Get stream information for the encoded stream. This can be used to
+  /// add a corresponding stream to a muxer with `with_stream`.
+  pub fn stream_info(&self) -> StreamInfo {
+    StreamInfo::new(
+      Self::STREAM_INDEX,
+      (&self.encoder).into(),
+      self.encoder_time_base,
+    )
+  }
+
+  /// Encode a single `ndarray` frame.
   /// 
   /// # Arguments
   /// 
-  /// * `encoder` - Encoder to apply settings to.
-  /// 
+  /// * `frame` - Frame to encode in `HWC` format and standard layout.
+  /// * `source_timestamp` - Frame timestamp of original source. This is
+  ///   necessary to make sure the output will be timed correctly.
+  /// 
+  /// # Returns
+  /// 
+  /// Zero or more encoded packets.
+  #[cfg(feature = "ndarray")]
+  pub fn encode(
+    &mut self,
+    frame: &Frame,
+    source_timestamp: &Time,
+  ) -> Result<Vec<Packet>> {
+    let (height, width, channels) = frame.dim();
+    if height != self.scaler_height as usize ||
+       width != self.scaler_width as usize ||
+       channels != 3 {
+      return Err(Error::InvalidFrameFormat);
+    }
+
+    let mut frame = convert_ndarray_to_frame_rgb24(frame)
+      .map_err(Error::BackendError)?;
+
+    frame.set_pts(
+      source_timestamp
+        .aligned(self.encoder_time_base)
+        .into_value());
+
+    self.encode_raw(frame)
+  }
+
+  /// Encode a single raw frame.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame` - Frame to encode.
+  /// 
+  /// # Returns
+  /// 
+  /// Zero or more encoded packets.
+  pub fn encode_raw(&mut self, frame: RawFrame) -> Result<Vec<Packet>> {
+    if frame.width() != self.scaler_width ||
+       frame.height() != self.scaler_height ||
+       frame.format() != FRAME_PIXEL_FORMAT {
+      return Err(Error::InvalidFrameFormat);
+    }
+
+    // Reformat frame to target pixel format.
+    let mut frame_scaled = RawFrame::empty();
+    self
+      .scaler
+      .run(&frame, &mut frame_scaled)
+      .map_err(Error::BackendError)?;
+    // Copy over PTS from old frame.
+    frame_scaled.set_pts(frame.pts());
+
+    // Producer key frame every once in a while
+    if self.frame_count % self.keyframe_interval == 0 {
+      frame_scaled.set_kind(AvFrameType::I);
+    }
+    // Encoders with a fixed quality scale take the quality from the frame.
+    if let Some(quality) = self.fixed_quality {
+      set_frame_quality(&mut frame_scaled, quality);
+    }
+    self.frame_count += 1;
+
+    self
+      .encoder
+      .send_frame(&*frame_scaled)
+      .map_err(Error::BackendError)?;
+
+    self.receive_packets()
+  }
+
+  /// Signal to the encoder that encoding has finished and return any
+  /// packets that were still buffered inside the encoder.
+  pub fn finish(&mut self) -> Result<Vec<Packet>> {
+    // Notify the encoder that the last frame has been sent.
+    self.encoder.send_eof()?;
+
+    self.receive_packets()
+  }
+
+  /// Pull all available encoded packets from the encoder.
+  fn receive_packets(&mut self) -> Result<Vec<Packet>> {
+    let mut packets = Vec::new();
+    loop {
+      let mut packet = AvPacket::empty();
+      match self.encoder.receive_packet(&mut packet) {
+        Ok(()) => {
+          packet.set_stream(Self::STREAM_INDEX);
+          packet.set_position(-1);
+          packets.push(Packet::new(packet, self.encoder_time_base));
+        },
+        Err(AvError::Other { errno }) if errno == EAGAIN
+          => break,
+        Err(AvError::Eof)
+          => break,
+        Err(err)
+          => return Err(err.into()),
+      }
+    }
+
+    Ok(packets)
+  }
+
+}
+
+/// Holds a logical combination of encoder settings.
+/// 
+/// Settings are created with one of the `for_*` constructors and can then
+/// be adjusted with the `with_*` builder methods.
+/// 
+/// # Example
+/// 
+/// ```
+/// let settings = Settings::for_h264_yuv420p(1280, 720, false)
+///   .with_frame_rate(25, 1)
+///   .with_gop_size(50)
+///   .with_keyframe_interval(50)
+///   .with_rate_control(RateControl::ConstantRateFactor(23.0))
+///   .with_max_bit_rate(2_000_000)
+///   .with_max_b_frames(2)
+///   .with_profile("high")
+///   .with_level("4.0");
+/// ```
+pub struct Settings<'o> {
+  codec_id: AvCodecId,
+  codec_names: &'static [&'static str],
+  width: u32,
+  height: u32,
+  pixel_format: AvPixel,
+  frame_rate: (i32, i32),
+  gop_size: Option<u32>,
+  keyframe_interval: Option<u64>,
+  rate_control: Option<RateControl>,
+  max_bit_rate: Option<usize>,
+  max_b_frames: Option<usize>,
+  profile: Option<String>,
+  level: Option<String>,
+  options: Options<'o>,
+}
+
+impl<'o> Settings<'o> {
+  /// This is the assumed FPS for the encoder to use if not configured
+  /// otherwise. Note that this does not need to be correct exactly.
+  const FRAME_RATE: i32 = 30;
+  /// Default interval (in frames) at which key frames are forced.
+  const KEY_FRAME_INTERVAL: u64 = 12;
+
+  /// Create encoder settings for an H264 stream with YUV420p pixel format.
+  /// This will encode to arguably the most widely compatible video file since
+  /// H264 is a common codec and YUV420p is the most commonly used pixel format.
+  pub fn for_h264_yuv420p(
+    width: usize,
+    height: usize,
+    realtime: bool,
+  ) -> Settings<'o> {
+    let options = if realtime {
+      Options::new_h264_realtime()
+    } else {
+      Options::new_h264()
+    };
+
+    Self::new(
+      AvCodecId::H264,
+      // Prefer libx264 over whatever default h264 encoder we have.
+      &["libx264"],
+      width,
+      height,
+      AvPixel::YUV420P,
+      options,
+    )
+  }
+
+  /// Create encoder settings for an H265 (HEVC) stream with YUV420p pixel
+  /// format. H265 achieves similar quality to H264 at a lower bit rate, but
+  /// is less widely supported.
+  pub fn for_hevc_yuv420p(
+    width: usize,
+    height: usize,
+    realtime: bool,
+  ) -> Settings<'o> {
+    let options = if realtime {
+      Options::new_hevc_realtime()
+    } else {
+      Options::new_hevc()
+    };
+
+    Self::new(
+      AvCodecId::HEVC,
+      &["libx265"],
+      width,
+      height,
+      AvPixel::YUV420P,
+      options,
+    )
+  }
+
+  /// Create encoder settings for a VP9 stream with YUV420p pixel format. By
+  /// default, the stream is encoded in constant quality mode.
+  pub fn for_vp9_yuv420p(
+    width: usize,
+    height: usize,
+    realtime: bool,
+  ) -> Settings<'o> {
+    let options = if realtime {
+      Options::new_vp9_realtime()
+    } else {
+      Options::new_vp9()
+    };
+
+    Self::new(
+        AvCodecId::VP9,
+        &["libvpx-vp9"],
+        width,
+        height,
+        AvPixel::YUV420P,
+        options,
+      )
+      .with_rate_control(RateControl::ConstantRateFactor(31.0))
+  }
+
+  /// Create encoder settings for an AV1 stream with YUV420p pixel format.
+  /// This uses the first AV1 encoder that is available: SVT-AV1, libaom or
+  /// rav1e. By default, the stream is encoded in constant quality mode.
+  pub fn for_av1_yuv420p(
+    width: usize,
+    height: usize,
+    realtime: bool,
+  ) -> Settings<'o> {
+    let options = if realtime {
+      Options::new_av1_realtime()
+    } else {
+      Options::new_av1()
+    };
+
+    Self::new(
+        AvCodecId::AV1,
+        &["libsvtav1", "libaom-av1", "librav1e"],
+        width,
+        height,
+        AvPixel::YUV420P,
+        options,
+      )
+      .with_rate_control(RateControl::ConstantRateFactor(30.0))
+  }
+
+  /// Create encoder settings for a lossless FFV1 stream with YUV420p pixel
+  /// format. Note that conversion from RGB to YUV420p is itself lossy, use
+  /// `for_ffv1_gbrp` to retain the exact RGB frames.
+  pub fn for_ffv1_yuv420p(
+    width: usize,
+    height: usize,
+  ) -> Settings<'o> {
+    Self::new_intra(
+      AvCodecId::FFV1,
+      &[],
+      width,
+      height,
+      AvPixel::YUV420P,
+      Options::new_ffv1(),
+    )
+  }
+
+  /// Create encoder settings for a lossless FFV1 stream with planar RGB
+  /// pixel format. Frames will be stored exactly as they are provided.
+  pub fn for_ffv1_gbrp(
+    width: usize,
+    height: usize,
+  ) -> Settings<'o> {
+    Self::new_intra(
+      AvCodecId::FFV1,
+      &[],
+      width,
+      height,
+      AvPixel::GBRP,
+      Options::new_ffv1(),
+    )
+  }
+
+  /// Create encoder settings for a lossless FFV1 stream with 8-bit grayscale
+  /// pixel format.
+  pub fn for_ffv1_gray8(
+    width: usize,
+    height: usize,
+  ) -> Settings<'o> {
+    Self::new_intra(
+      AvCodecId::FFV1,
+      &[],
+      width,
+      height,
+      AvPixel::GRAY8,
+      Options::new_ffv1(),
+    )
+  }
+
+  /// Create encoder settings for an MJPEG stream with full range YUV420p
+  /// pixel format. Each frame is encoded as a separate JPEG image.
+  pub fn for_mjpeg_yuvj420p(
+    width: usize,
+    height: usize,
+  ) -> Settings<'o> {
+    Self::new_intra(
+      AvCodecId::MJPEG,
+      &[],
+      width,
+      height,
+      AvPixel::YUVJ420P,
+      Options::new_mjpeg(),
+    )
+  }
+
+  /// Create encoder settings for a ProRes 422 HQ stream with 10-bit YUV422p
+  /// pixel format. ProRes should be written to a MOV container.
+  pub fn for_prores_yuv422p10le(
+    width: usize,
+    height: usize,
+  ) -> Settings<'o> {
+    Self::new_intra(
+      AvCodecId::PRORES,
+      // The `prores_ks` encoder supports all profiles.
+      &["prores_ks"],
+      width,
+      height,
+      AvPixel::YUV422P10LE,
+      Options::new_prores(),
+    )
+  }
+
+  /// Create encoder settings for an intra-only codec, where every frame is
+  /// a key frame.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `codec_id` - Codec to encode with.
+  /// * `codec_names` - Names of preferred encoders for the codec, in order
+  ///   of preference.
+  /// * `width` - Frame width.
+  /// * `height` - Frame height.
+  /// * `pixel_format` - Pixel format to encode with.
+  /// * `options` - Encoder options.
+  fn new_intra(
+    codec_id: AvCodecId,
+    codec_names: &'static [&'static str],
+    width: usize,
+    height: usize,
+    pixel_format: AvPixel,
+    options: Options<'o>,
+  ) -> Settings<'o> {
+    Self::new(
+        codec_id,
+        codec_names,
+        width,
+        height,
+        pixel_format,
+        options,
+      )
+      .with_gop_size(1)
+      .with_keyframe_interval(1)
+      .with_max_b_frames(0)
+  }
+
+  /// Create encoder settings with defaults for everything but the codec,
+  /// dimensions, pixel format and options.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `codec_id` - Codec to encode with.
+  /// * `codec_names` - Names of preferred encoders for the codec, in order
+  ///   of preference.
+  /// * `width` - Frame width.
+  /// * `height` - Frame height.
+  /// * `pixel_format` - Pixel format to encode with.
+  /// * `options` - Encoder options.
+  fn new(
+    codec_id: AvCodecId,
+    codec_names: &'static [&'static str],
+    width: usize,
+    height: usize,
+    pixel_format: AvPixel,
+    options: Options<'o>,
+  ) -> Settings<'o> {
+    Self {
+      codec_id,
+      codec_names,
+      width: width as u32,
+      height: height as u32,
+      pixel_format,
+      frame_rate: (Self::FRAME_RATE, 1),
+      gop_size: None,
+      keyframe_interval: None,
+      rate_control: None,
+      max_bit_rate: None,
+      max_b_frames: None,
+      profile: None,
+      level: None,
+      options,
+    }
+  }
+
+  /// Set the frame rate as a fraction, for example `(30000, 1001)` for
+  /// 29.97 FPS.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `numerator` - Frame rate numerator.
+  /// * `denominator` - Frame rate denominator.
+  pub fn with_frame_rate(mut self, numerator: i32, denominator: i32) -> Self {
+    self.frame_rate = (numerator, denominator);
+    self
+  }
+
+  /// Set the GOP (group of pictures) size, which is the maximum distance
+  /// in frames between two key frames.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `gop_size` - Maximum number of frames in a GOP.
+  pub fn with_gop_size(mut self, gop_size: u32) -> Self {
+    self.gop_size = Some(gop_size);
+    self
+  }
+
+  /// Set the interval (in frames) at which the encoder forces a key frame.
+  /// If not set, a key frame is forced every 12 frames, or every GOP if the
+  /// GOP size is smaller than that.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `keyframe_interval` - Number of frames between forced key frames.
+  pub fn with_keyframe_interval(mut self, keyframe_interval: u64) -> Self {
+    self.keyframe_interval = Some(keyframe_interval);
+    self
+  }
+
+  /// Set the rate control mode.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `rate_control` - Rate control mode and its parameter.
+  pub fn with_rate_control(mut self, rate_control: RateControl) -> Self {
+    self.rate_control = Some(rate_control);
+    self
+  }
+
+  /// Set the maximum bit rate in bits per second. This caps the bit rate
+  /// when using constant rate factor, constant quantizer or average bit
+  /// rate control.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `max_bit_rate` - Maximum bit rate.
+  pub fn with_max_bit_rate(mut self, max_bit_rate: usize) -> Self {
+    self.max_bit_rate = Some(max_bit_rate);
+    self
+  }
+
+  /// Set the maximum number of consecutive B-frames. Use zero to disable
+  /// B-frames altogether.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `max_b_frames` - Maximum number of B-frames.
+  pub fn with_max_b_frames(mut self, max_b_frames: usize) -> Self {
+    self.max_b_frames = Some(max_b_frames);
+    self
+  }
+
+  /// Set the codec profile, for example `"baseline"`, `"main"` or `"high"`
+  /// for H.264.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `profile` - Profile name.
+  pub fn with_profile(mut self, profile: &str) -> Self {
+    self.profile = Some(profile.to_string());
+    self
+  }
+
+  /// Set the codec level, for example `"3.1"` or `"4.0"` for H.264.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `level` - Level name.
+  pub fn with_level(mut self, level: &str) -> Self {
+    self.level = Some(level.to_string());
+    self
+  }
+
+  /// Check whether the combination of settings is possible.
+  /// 
+  /// Note: This is called automatically when creating an encoder.
+  pub fn validate(&self) -> Result<()> {
+    if self.width == 0 || self.height == 0 {
+      return Err(Error::InvalidEncoderSettings(
+        "width and height must be non-zero"));
+    }
+
+    let (frame_rate_num, frame_rate_den) = self.frame_rate;
+    if frame_rate_num <= 0 || frame_rate_den <= 0 {
+      return Err(Error::InvalidEncoderSettings(
+        "frame rate must be positive"));
+    }
+
+    if self.gop_size == Some(0) {
+      return Err(Error::InvalidEncoderSettings(
+        "GOP size must be non-zero"));
+    }
+
+    if self.keyframe_interval == Some(0) {
+      return Err(Error::InvalidEncoderSettings(
+        "key frame interval must be non-zero"));
+    }
+
+    if let (Some(keyframe_interval), Some(gop_size)) = (self.keyframe_interval, self.gop_size) {
+      if keyframe_interval > gop_size as u64 {
+        return Err(Error::InvalidEncoderSettings(
+          "key frame interval cannot be larger than GOP size"));
+      }
+    }
+
+    if let (Some(max_b_frames), Some(gop_size)) = (self.max_b_frames, self.gop_size) {
+      if max_b_frames >= gop_size as usize {
+        return Err(Error::InvalidEncoderSettings(
+          "number of B-frames must be smaller than GOP size"));
+      }
+    }
+
+    match self.rate_control {
+      Some(RateControl::ConstantRateFactor(crf)) if crf.is_nan() || crf < 0.0 => {
+        return Err(Error::InvalidEncoderSettings(
+          "constant rate factor must not be negative"));
+      },
+      Some(RateControl::AverageBitRate(0)) |
+      Some(RateControl::ConstantBitRate(0)) => {
+        return Err(Error::InvalidEncoderSettings(
+          "bit rate must be non-zero"));
+      },
+      Some(RateControl::AverageBitRate(bit_rate)) => {
+        if let Some(max_bit_rate) = self.max_bit_rate {
+          if max_bit_rate < bit_rate {
+            return Err(Error::InvalidEncoderSettings(
+              "maximum bit rate cannot be lower than average bit rate"));
+          }
+        }
+      },
+      Some(RateControl::ConstantBitRate(_)) => {
+        if self.max_bit_rate.is_some() {
+          return Err(Error::InvalidEncoderSettings(
+            "maximum bit rate cannot be combined with constant bit rate"));
+        }
+      },
+      _ => {},
+    }
+
+    if self.max_bit_rate == Some(0) {
+      return Err(Error::InvalidEncoderSettings(
+        "maximum bit rate must be non-zero"));
+    }
+
+    Ok(())
+  }
+
+  /// Get the interval (in frames) at which key frames are forced. Unless
+  /// set explicitly, this is the default interval, clamped to the GOP size.
+  fn keyframe_interval(&self) -> u64 {
+    match (self.keyframe_interval, self.gop_size) {
+      (Some(keyframe_interval), _) => keyframe_interval,
+      (None, Some(gop_size)) => Self::KEY_FRAME_INTERVAL.min(gop_size as u64),
+      (None, None) => Self::KEY_FRAME_INTERVAL,
+    }
+  }
+
+  /// Apply the settings to an encoder.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `encoder` - Encoder to apply settings to.
+  /// 
   /// # Returns
   /// 
   /// New encoder with settings applied.
@@ -478,16 +1433,185 @@ impl<'o> Settings<'o> {
     encoder.set_width(self.width);
     encoder.set_height(self.height);
     encoder.set_format(self.pixel_format);
-    encoder.set_frame_rate(Some((Self::FRAME_RATE, 1)));
+    encoder.set_frame_rate(Some(self.frame_rate));
+    if let Some(gop_size) = self.gop_size {
+      encoder.set_gop(gop_size);
+    }
+    if let Some(max_b_frames) = self.max_b_frames {
+      encoder.set_max_b_frames(max_b_frames);
+    }
+    match self.rate_control {
+      Some(RateControl::AverageBitRate(bit_rate)) => {
+        encoder.set_bit_rate(bit_rate);
+      },
+      Some(RateControl::ConstantBitRate(bit_rate)) => {
+        encoder.set_bit_rate(bit_rate);
+        encoder.set_max_bit_rate(bit_rate);
+      },
+      _ => {},
+    }
+    if let Some(max_bit_rate) = self.max_bit_rate {
+      encoder.set_max_bit_rate(max_bit_rate);
+    }
+    encoder
+  }
+
+  /// Get codec. This tries the preferred encoders first. If none of them
+  /// are available, then use whatever default encoder we have for the
+  /// codec.
+  fn codec(&self) -> Result<AvCodec> {
+    self
+      .codec_names
+      .iter()
+      .find_map(|name| ffmpeg::encoder::find_by_name(name))
+      .or_else(|| ffmpeg::encoder::find(self.codec_id))
+      .ok_or(Error::CodecNotAvailable(self.codec_id.name()))
+  }
+
+  /// Get encoder options. This includes the options that correspond to
+  /// the rate control, profile and level settings.
+  fn options(&self) -> Options<'o> {
+    let mut options = self.options.clone();
+
+    match self.rate_control {
+      Some(RateControl::ConstantRateFactor(crf)) => {
+        options.set("crf", &crf.to_string());
+        // Some encoders (like VP9 and AV1) only do pure constant quality
+        // encoding when the bit rate is zero.
+        if self.max_bit_rate.is_none() {
+          options.set("b", "0");
+        }
+      },
+      Some(RateControl::ConstantQuantizer(qp)) => {
+        options.set("qp", &qp.to_string());
+      },
+      Some(RateControl::ConstantBitRate(bit_rate)) => {
+        options.set("minrate", &bit_rate.to_string());
+        options.set("bufsize", &bit_rate.to_string());
+      },
+      _ => {},
+    }
+
+    // The rate control buffer is needed by the encoder to enforce the
+    // maximum bit rate. We use a one second buffer.
+    if let Some(max_bit_rate) = self.max_bit_rate {
+      options.set("bufsize", &max_bit_rate.to_string());
+    }
+
+    if let Some(profile) = &self.profile {
+      options.set("profile", profile);
+    }
+
+    if let Some(level) = &self.level {
+      options.set("level", level);
+    }
+
+    options
+  }
+
+}
+
+/// Rate control modes that determine how the encoder distributes bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RateControl {
+  /// Constant quality, expressed as a constant rate factor (CRF). Lower
+  /// values mean higher quality. For H.264, the useful range is 0 to 51.
+  ConstantRateFactor(f32),
+  /// Constant quantization parameter (QP).
+  ConstantQuantizer(u32),
+  /// Average bit rate in bits per second.
+  AverageBitRate(usize),
+  /// Constant bit rate in bits per second.
+  ConstantBitRate(usize),
+}
+
+/// Holds a logical combination of audio encoder settings.
+pub struct AudioSettings<'o> {
+  codec_id: AvCodecId,
+  codec_name: &'static str,
+  sample_rate: u32,
+  channels: u16,
+  bit_rate: usize,
+  options: Options<'o>,
+}
+
+impl<'o> AudioSettings<'o> {
+
+  /// Create audio encoder settings for an AAC stream. AAC is the most
+  /// widely supported audio codec in the MP4 container format.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `sample_rate` - Output sample rate.
+  /// * `channels` - Number of output channels.
+  pub fn for_aac(
+    sample_rate: u32,
+    channels: u16,
+  ) -> AudioSettings<'o> {
+    Self {
+      codec_id: AvCodecId::AAC,
+      codec_name: "libfdk_aac",
+      sample_rate,
+      channels,
+      bit_rate: 128_000,
+      options: Options::default(),
+    }
+  }
+
+  /// Create audio encoder settings for an Opus stream. Opus only operates
+  /// at 48 kHz, so samples will be resampled to that rate.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `channels` - Number of output channels.
+  pub fn for_opus(
+    channels: u16,
+  ) -> AudioSettings<'o> {
+    Self {
+      codec_id: AvCodecId::OPUS,
+      codec_name: "libopus",
+      sample_rate: 48000,
+      channels,
+      bit_rate: 96_000,
+      options: Options::default(),
+    }
+  }
+
+  /// Apply the settings to an audio encoder.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `encoder` - Encoder to apply settings to.
+  /// 
+  /// # Returns
+  /// 
+  /// New encoder with settings applied.
+  fn apply_to(&self, mut encoder: AvAudioEncoder) -> AvAudioEncoder {
+    encoder.set_rate(self.sample_rate as i32);
+    encoder.set_channel_layout(AvChannelLayout::default(self.channels as i32));
+    encoder.set_channels(self.channels as i32);
+    encoder.set_format(self.sample_format());
+    encoder.set_bit_rate(self.bit_rate);
     encoder
   }
 
   /// Get codec.
   fn codec(&self) -> Option<AvCodec> {
-    // Try to use the libx264 decoder. If it is not available, then use
-    // use whatever default h264 decoder we have.
-    Some(ffmpeg::encoder::find_by_name("libx264")
-      .unwrap_or(ffmpeg::encoder::find(AvCodecId::H264)?))
+    // Try to use the preferred external encoder. If it is not available,
+    // then use whatever default encoder we have.
+    Some(ffmpeg::encoder::find_by_name(self.codec_name)
+      .unwrap_or(ffmpeg::encoder::find(self.codec_id)?))
+  }
+
+  /// Get the sample format to encode with. This is the first sample format
+  /// supported by the codec.
+  fn sample_format(&self) -> AvSample {
+    self
+      .codec()
+      .and_then(|codec| codec.audio().ok())
+      .and_then(|codec| codec.formats())
+      .and_then(|mut formats| formats.next())
+      .unwrap_or(AvSample::F32(AvSampleType::Planar))
   }
 
   /// Get encoder options.
@@ -496,3 +1620,190 @@ impl<'o> Settings<'o> {
   }
 
 }
+
+/// Internal structure that holds the state of the audio stream of an
+/// encoder.
+struct AudioStream {
+  writer_stream_index: usize,
+  encoder: AvAudioEncoder,
+  encoder_time_base: AvRational,
+  resampler: Option<AvResampler>,
+  fifo: AudioFifo,
+  frame_size: usize,
+  next_pts: Option<i64>,
+}
+
+impl AudioStream {
+
+  /// Resample the frame, buffer the samples and encode as many frames as
+  /// possible.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame` - Audio frame to encode.
+  /// 
+  /// # Returns
+  /// 
+  /// Encoded packets, if any.
+  fn encode(&mut self, frame: RawAudioFrame) -> Result<Vec<AvPacket>> {
+    if self.next_pts.is_none() {
+      self.next_pts = Some(frame.pts().unwrap_or(0));
+    }
+
+    let frame = self.resample(frame)?;
+    self.fifo.write(&frame)?;
+
+    let mut packets = Vec::new();
+    while self.fifo.size() > 0 && self.fifo.size() >= self.frame_size {
+      packets.extend(self.encode_from_fifo()?);
+    }
+
+    Ok(packets)
+  }
+
+  /// Flush the resampler and the buffered samples, and drain the encoder.
+  /// 
+  /// # Returns
+  /// 
+  /// Remaining encoded packets.
+  fn flush(&mut self) -> Result<Vec<AvPacket>> {
+    // Maximum number of invocations to `encoder_receive_packet`
+    // to drain the items still on the queue before giving up.
+    const MAX_DRAIN_ITERATIONS: u32 = 100;
+
+    let mut packets = Vec::new();
+
+    if let Some(resampler) = self.resampler.as_mut() {
+      let capacity = get_resampler_out_samples(resampler, 0);
+      if capacity > 0 {
+        let mut frame = RawAudioFrame::new(
+          self.encoder.format(),
+          capacity,
+          self.encoder.channel_layout());
+        resampler.flush(&mut frame)?;
+        self.fifo.write(&frame)?;
+      }
+    }
+
+    // The last frame is allowed to be smaller than the frame size.
+    while self.fifo.size() > 0 {
+      packets.extend(self.encode_from_fifo()?);
+    }
+
+    // Notify the encoder that the last frame has been sent.
+    self.encoder.send_eof()?;
+
+    // We need to drain the items still in the encoders queue.
+    for _ in 0..MAX_DRAIN_ITERATIONS {
+      match self.encoder_receive_packet() {
+        Ok(Some(packet))
+          => packets.push(packet),
+        Ok(None)
+          => continue,
+        Err(_)
+          => break,
+      }
+    }
+
+    Ok(packets)
+  }
+
+  /// Take one encoder frame worth of samples from the FIFO and encode it.
+  fn encode_from_fifo(&mut self) -> Result<Vec<AvPacket>> {
+    let num_samples = if self.frame_size > 0 {
+      self.frame_size
+    } else {
+      // Encoder accepts frames of any size.
+      self.fifo.size()
+    };
+
+    let mut frame = self.fifo.read(
+      num_samples,
+      self.encoder.channel_layout())?;
+    frame.set_rate(self.encoder.rate());
+
+    let pts = self.next_pts.unwrap_or(0);
+    frame.set_pts(Some(pts));
+    self.next_pts = Some(pts + frame.samples() as i64);
+
+    self
+      .encoder
+      .send_frame(&*frame)
+      .map_err(Error::BackendError)?;
+
+    let mut packets = Vec::new();
+    while let Some(packet) = self.encoder_receive_packet()? {
+      packets.push(packet);
+    }
+
+    Ok(packets)
+  }
+
+  /// Convert the frame to the sample format, channel layout and sample
+  /// rate of the encoder. The resampler is (re)created whenever the
+  /// incoming frames change format.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame` - Frame to resample.
+  fn resample(&mut self, mut frame: RawAudioFrame) -> Result<RawAudioFrame> {
+    if frame.channel_layout().is_empty() {
+      frame.set_channel_layout(AvChannelLayout::default(frame.channels() as i32));
+    }
+    if frame.rate() == 0 {
+      frame.set_rate(self.encoder.rate());
+    }
+
+    let resampler_matches = self
+      .resampler
+      .as_ref()
+      .map(|resampler| {
+        let input = resampler.input();
+        input.format == frame.format() &&
+        input.channel_layout == frame.channel_layout() &&
+        input.rate == frame.rate()
+      })
+      .unwrap_or(false);
+
+    if !resampler_matches {
+      self.resampler = Some(AvResampler::get(
+        frame.format(),
+        frame.channel_layout(),
+        frame.rate(),
+        self.encoder.format(),
+        self.encoder.channel_layout(),
+        self.encoder.rate())?);
+    }
+
+    let resampler = self.resampler.as_mut().unwrap();
+    // Allocate the output frame up front to make sure it is large enough
+    // to hold all output samples, even when upsampling.
+    let capacity = get_resampler_out_samples(resampler, frame.samples());
+    let mut frame_resampled = RawAudioFrame::new(
+      self.encoder.format(),
+      capacity,
+      self.encoder.channel_layout());
+    resampler
+      .run(&frame, &mut frame_resampled)
+      .map_err(Error::BackendError)?;
+
+    Ok(frame_resampled)
+  }
+
+  /// Pull an encoded packet from the encoder. This function also handles
+  /// the possible `EAGAIN` result, in which case we just need to go
+  /// again.
+  fn encoder_receive_packet(&mut self) -> Result<Option<AvPacket>> {
+    let mut packet = AvPacket::empty();
+    let encode_result = self.encoder.receive_packet(&mut packet);
+    match encode_result {
+      Ok(())
+        => Ok(Some(packet)),
+      Err(AvError::Other { errno }) if errno == EAGAIN
+        => Ok(None),
+      Err(err)
+        => Err(err.into()),
+    }
+  }
+
+}
@@ -4,9 +4,48 @@ use std::fmt;
 use std::error;
 
 use ffmpeg::Error as FfmpegError;
+use ffmpeg::util::error::{
+  EAGAIN,
+  ECONNABORTED,
+  ECONNREFUSED,
+  ECONNRESET,
+  EHOSTUNREACH,
+  ENETDOWN,
+  ENETRESET,
+  ENETUNREACH,
+  ENOTCONN,
+  EPIPE,
+  ETIMEDOUT,
+};
+
+use super::io::Locator;
 
 /// Represents video I/O Errors. Some errors are generated
 /// by the ffmpeg backend, and are wrapped in `BackendError`.
+/// 
+/// Errors returned by readers, decoders, encoders and muxers are usually
+/// wrapped in `WithContext`, which describes the operation that failed.
+/// Use the predicates like `is_eof` and `is_retryable` to inspect errors
+/// regardless of context.
+/// 
+/// # Breaking change
+/// 
+/// Since version 0.2, matching on a bare variant like `Error::ReadExhausted`
+/// no longer matches errors returned by readers, decoders, encoders and
+/// muxers, because they are wrapped in `WithContext`. Such code still
+/// compiles, so it must be migrated by hand: match on `err.root()` instead,
+/// or use a predicate like `err.is_eof()`.
+/// 
+/// ```
+/// match decoder.decode() {
+///   Ok((ts, frame)) => { /* ... */ },
+///   Err(err) if err.is_eof() => break,
+///   Err(err) => match err.root() {
+///     Error::InvalidFrameFormat => { /* ... */ },
+///     _ => return Err(err),
+///   },
+/// }
+/// ```
 #[derive(Debug, Clone)]
 pub enum Error {
   ReadExhausted,
@@ -15,11 +54,139 @@ pub enum Error {
   InvalidExtraData,
   MissingCodecParameters,
   UnsupporedCodecParameterSets,
+  UnsupportedCodecString,
+  UnsupportedBitstreamConversion,
+  InvalidBitstream,
+  InvalidEncoderSettings(&'static str),
+  CodecNotAvailable(&'static str),
+  Interrupted,
+  TimedOut,
+  StreamParametersChanged,
+  MissingTimestamp,
+  NegativeTimestamp,
+  InvalidTimecode,
   BackendError(FfmpegError),
+  WithContext(Box<Context>, Box<Error>),
+}
+
+impl Error {
+
+  /// Get the context of the error, if any.
+  pub fn context(&self) -> Option<&Context> {
+    match *self {
+      Error::WithContext(ref context, _) => Some(context),
+      _ => None,
+    }
+  }
+
+  /// Get the underlying error without context.
+  pub fn root(&self) -> &Error {
+    match *self {
+      Error::WithContext(_, ref internal) => internal.root(),
+      ref err => err,
+    }
+  }
+
+  /// Whether or not the error indicates that the end of the stream was
+  /// reached.
+  pub fn is_eof(&self) -> bool {
+    matches!(
+      self.root(),
+      Error::ReadExhausted |
+      Error::BackendError(FfmpegError::Eof))
+  }
+
+  /// Whether or not the error is caused by cancellation through a
+  /// `CancellationToken`.
+  pub fn is_interrupted(&self) -> bool {
+    matches!(self.root(), Error::Interrupted)
+  }
+
+  /// Whether or not the error is caused by an operation that took too long,
+  /// either because a deadline passed or because the backend timed out.
+  pub fn is_timeout(&self) -> bool {
+    matches!(
+      self.root(),
+      Error::TimedOut |
+      Error::BackendError(FfmpegError::Other { errno: ETIMEDOUT }))
+  }
+
+  /// Whether or not the error is caused by a network problem, such as a
+  /// connection that was refused, reset or timed out, or an HTTP error.
+  pub fn is_network(&self) -> bool {
+    match self.root() {
+      Error::TimedOut => true,
+      Error::BackendError(internal) => matches!(
+        internal,
+        FfmpegError::HttpBadRequest |
+        FfmpegError::HttpUnauthorized |
+        FfmpegError::HttpForbidden |
+        FfmpegError::HttpNotFound |
+        FfmpegError::HttpOther4xx |
+        FfmpegError::HttpServerError |
+        FfmpegError::Other {
+          errno:
+            ECONNABORTED |
+            ECONNREFUSED |
+            ECONNRESET |
+            EHOSTUNREACH |
+            ENETDOWN |
+            ENETRESET |
+            ENETUNREACH |
+            ENOTCONN |
+            EPIPE |
+            ETIMEDOUT
+        }),
+      _ => false,
+    }
+  }
+
+  /// Whether or not retrying the operation (possibly after reconnecting)
+  /// might succeed. This is the case for transient errors, like network
+  /// problems, timeouts and temporarily unavailable resources.
+  pub fn is_retryable(&self) -> bool {
+    if self.is_network() || self.is_timeout() {
+      return true;
+    }
+
+    match self.root() {
+      Error::WriteRetryLimitReached => true,
+      Error::BackendError(FfmpegError::Other { errno }) => *errno == EAGAIN,
+      _ => false,
+    }
+  }
+
+  /// Attach context to the error. If the error already has context, that
+  /// context is kept since it is the most specific.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `operation` - Operation that failed.
+  /// * `locator` - Source or destination the operation was performed on.
+  /// * `stream_index` - Index of stream the operation was performed on.
+  pub(crate) fn with_context(
+    self,
+    operation: Operation,
+    locator: Option<&Locator>,
+    stream_index: Option<usize>,
+  ) -> Error {
+    match self {
+      Error::WithContext(..) => self,
+      internal => Error::WithContext(
+        Box::new(Context {
+          operation,
+          locator: locator.cloned(),
+          stream_index,
+        }),
+        Box::new(internal),
+      ),
+    }
+  }
+
 }
 
 impl error::Error for Error {
-  
+
   fn source(&self) -> Option<&(dyn error::Error + 'static)> {
     match *self {
       Error::ReadExhausted => None,
@@ -28,8 +195,21 @@ impl error::Error for Error {
       Error::InvalidExtraData => None,
       Error::MissingCodecParameters => None,
       Error::UnsupporedCodecParameterSets => None,
+      Error::UnsupportedCodecString => None,
+      Error::UnsupportedBitstreamConversion => None,
+      Error::InvalidBitstream => None,
+      Error::InvalidEncoderSettings(_) => None,
+      Error::CodecNotAvailable(_) => None,
+      Error::Interrupted => None,
+      Error::TimedOut => None,
+      Error::StreamParametersChanged => None,
+      Error::MissingTimestamp => None,
+      Error::NegativeTimestamp => None,
+      Error::InvalidTimecode => None,
       Error::BackendError(ref internal) =>
         Some(internal),
+      Error::WithContext(_, ref internal) =>
+        Some(internal.as_ref()),
     }
   }
 
@@ -51,8 +231,32 @@ impl fmt::Display for Error {
         write!(f, "codec parameters missing"),
       Error::UnsupporedCodecParameterSets =>
         write!(f, "extracting parameter sets for this codec is not suppored"),
+      Error::UnsupportedCodecString =>
+        write!(f, "producing a codec string for this codec is not supported"),
+      Error::UnsupportedBitstreamConversion =>
+        write!(f, "converting the bitstream format of this codec is not supported"),
+      Error::InvalidBitstream =>
+        write!(f, "packet payload does not match bitstream format"),
+      Error::InvalidEncoderSettings(reason) =>
+        write!(f, "invalid encoder settings: {}", reason),
+      Error::CodecNotAvailable(codec) =>
+        write!(f, "no encoder for codec {} available in linked ffmpeg", codec),
+      Error::Interrupted =>
+        write!(f, "operation interrupted by cancellation"),
+      Error::TimedOut =>
+        write!(f, "operation did not finish before its deadline"),
+      Error::StreamParametersChanged =>
+        write!(f, "stream parameters of source changed after reconnecting"),
+      Error::MissingTimestamp =>
+        write!(f, "timestamp has no value"),
+      Error::NegativeTimestamp =>
+        write!(f, "timestamp is negative"),
+      Error::InvalidTimecode =>
+        write!(f, "timecode is invalid or not valid at this frame rate"),
       Error::BackendError(ref internal) =>
         internal.fmt(f),
+      Error::WithContext(ref context, ref internal) =>
+        write!(f, "{}: {}", context, internal),
     }
   }
 
@@ -65,3 +269,55 @@ impl From<FfmpegError> for Error {
   }
 
 }
+
+/// Describes where an error occurred.
+#[derive(Debug, Clone)]
+pub struct Context {
+  /// Operation that failed.
+  pub operation: Operation,
+  /// Source or destination the operation was performed on, if known.
+  pub locator: Option<Locator>,
+  /// Index of stream the operation was performed on, if applicable.
+  pub stream_index: Option<usize>,
+}
+
+impl fmt::Display for Context {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} failed", self.operation)?;
+    if let Some(ref locator) = self.locator {
+      write!(f, " on {}", locator)?;
+    }
+    if let Some(stream_index) = self.stream_index {
+      write!(f, " (stream {})", stream_index)?;
+    }
+    Ok(())
+  }
+
+}
+
+/// Kind of operation during which an error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+  Open,
+  Read,
+  Seek,
+  Decode,
+  Encode,
+  Mux,
+}
+
+impl fmt::Display for Operation {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Operation::Open => write!(f, "open"),
+      Operation::Read => write!(f, "read"),
+      Operation::Seek => write!(f, "seek"),
+      Operation::Decode => write!(f, "decode"),
+      Operation::Encode => write!(f, "encode"),
+      Operation::Mux => write!(f, "mux"),
+    }
+  }
+
+}
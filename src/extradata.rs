@@ -1,4 +1,11 @@
-use super::Error;
+use super::{
+  Error,
+  sps::{
+    BitReader,
+    SequenceParameterSet,
+    remove_emulation_prevention,
+  },
+};
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -21,6 +28,20 @@ pub type Sps<'buf> = &'buf [u8];
 /// constituent contents, and provide to the caller only the PPS bytes.
 pub type Pps<'buf> = Vec<&'buf [u8]>;
 
+/// Represents borrowed byte stream representations of the parameter sets
+/// of an H.265/HEVC stream: the Video Parameter Sets (VPSs), Sequence
+/// Parameter Sets (SPSs) and Picture Parameter Sets (PPSs) as defined in
+/// Section 7.3.2 in the Recommendation H.265.
+/// 
+/// Each parameter set is a complete NAL unit (including its two byte NAL
+/// unit header) without start code or length prefix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParameterSetsHevc<'buf> {
+  pub vps: Vec<&'buf [u8]>,
+  pub sps: Vec<&'buf [u8]>,
+  pub pps: Vec<&'buf [u8]>,
+}
+
 /// Extract the Sequence Parameter Set (SPS) and Picture Parameter Sets
 /// (PPSs) from an H.264 stream `extradata` bytes (as provided by the
 /// `libavcodec` backend).
@@ -130,6 +151,324 @@ fn extract_parameter_sets_from_extradata_h264_avc_annexb<'buf>(
   }
 }
 
+/// Extract the Video Parameter Sets (VPSs), Sequence Parameter Sets (SPSs)
+/// and Picture Parameter Sets (PPSs) from an H.265/HEVC stream `extradata`
+/// bytes (as provided by the `libavcodec` backend).
+/// 
+/// # Arguments
+/// 
+/// * `extradata_bytes` - Borrowed slice pointing to extradata bytes.
+/// 
+/// # Returns
+/// 
+/// `ParameterSetsHevc` or error. At least one of each type of parameter
+/// set must be present.
+pub fn extract_parameter_sets_hevc<'buf>(
+  extradata_bytes: &'buf [u8],
+) -> Result<ParameterSetsHevc<'buf>> {
+  if extradata_bytes.len() <= 3 {
+    return Err(Error::InvalidExtraData);
+  }
+
+  let parameter_sets = if is_hvcc(extradata_bytes) {
+    extract_parameter_sets_from_extradata_hevc_hvcc(extradata_bytes)?
+  } else {
+    extract_parameter_sets_from_extradata_hevc_annexb(extradata_bytes)
+  };
+
+  if !parameter_sets.vps.is_empty() &&
+     !parameter_sets.sps.is_empty() &&
+     !parameter_sets.pps.is_empty() {
+    Ok(parameter_sets)
+  } else {
+    Err(Error::InvalidExtraData)
+  }
+}
+
+/// Whether or not H.265/HEVC extradata is an `hvcC` record, as opposed to
+/// parameter sets in Annex B format.
+/// 
+/// # Arguments
+/// 
+/// * `extradata_bytes` - Borrowed slice pointing to extradata bytes.
+pub(crate) fn is_hvcc(extradata_bytes: &[u8]) -> bool {
+  // Annex B extradata always starts with a start code. Note that the
+  // configuration version of `hvcC` should be 1, but some muxers write
+  // zero, so like the backend we only rule out start codes.
+  extradata_bytes.len() > 3 &&
+    (extradata_bytes[0] != 0x00 ||
+     extradata_bytes[1] != 0x00 ||
+     extradata_bytes[2] > 0x01)
+}
+
+/// Extract parameter sets from HEVC stream in `hvcC` format (the HEVC
+/// decoder configuration record defined in ISO/IEC 14496-15). This is the
+/// HEVC counterpart of AVCC, used with the MP4 and Matroska containers.
+fn extract_parameter_sets_from_extradata_hevc_hvcc<'buf>(
+  bytes: &'buf [u8],
+) -> Result<ParameterSetsHevc<'buf>> {
+  // The fixed part of the record is 23 bytes, of which the last holds
+  // the number of NAL unit arrays.
+  const HEADER_SIZE: usize = 23;
+
+  let mut parameter_sets = ParameterSetsHevc {
+    vps: Vec::new(),
+    sps: Vec::new(),
+    pps: Vec::new(),
+  };
+
+  if bytes.len() < HEADER_SIZE {
+    return Err(Error::InvalidExtraData);
+  }
+
+  let num_arrays = bytes[HEADER_SIZE - 1];
+  let mut p = HEADER_SIZE;
+  for _ in 0..num_arrays {
+    if bytes[p..].len() < 3 {
+      return Err(Error::InvalidExtraData);
+    }
+
+    let nal_type = bytes[p] & 0x3f;
+    let num_nals = u16::from_be_bytes([bytes[p + 1], bytes[p + 2]]);
+    p += 3;
+
+    for _ in 0..num_nals {
+      if bytes[p..].len() < 2 {
+        return Err(Error::InvalidExtraData);
+      }
+
+      let nal_size = u16::from_be_bytes([bytes[p], bytes[p + 1]]) as usize;
+      if bytes[p + 2..].len() < nal_size {
+        return Err(Error::InvalidExtraData);
+      }
+
+      let nal = &bytes[p + 2..p + 2 + nal_size];
+      match nal_type {
+        32 /* VPS */ => parameter_sets.vps.push(nal),
+        33 /* SPS */ => parameter_sets.sps.push(nal),
+        34 /* PPS */ => parameter_sets.pps.push(nal),
+        _ => {}
+      };
+
+      p += 2 + nal_size;
+    }
+  }
+
+  Ok(parameter_sets)
+}
+
+/// Extract parameter sets from HEVC stream in Annex B format. HEVC uses
+/// the same start codes as H.264, but has a two byte NAL unit header with
+/// the NAL unit type in bits 1 through 6 of the first byte.
+fn extract_parameter_sets_from_extradata_hevc_annexb<'buf>(
+  bytes: &'buf [u8],
+) -> ParameterSetsHevc<'buf> {
+  let mut index_current = find_avc_start_code(bytes, 0)
+    .map(|(_, index_next)| index_next);
+
+  let mut parameter_sets = ParameterSetsHevc {
+    vps: Vec::new(),
+    sps: Vec::new(),
+    pps: Vec::new(),
+  };
+
+  while let Some(index) = index_current {
+    let (end, index_next) = match find_avc_start_code(bytes, index) {
+      Some((end, index_next))
+        => (end, Some(index_next)),
+      None
+        => (bytes.len(), None)
+    };
+    let nal = &bytes[index..end];
+    if nal.len() >= 2 {
+      let nal_type = (nal[0] >> 1) & 0x3f;
+      match nal_type {
+        32 /* VPS */ => parameter_sets.vps.push(nal),
+        33 /* SPS */ => parameter_sets.sps.push(nal),
+        34 /* PPS */ => parameter_sets.pps.push(nal),
+        _ => {}
+      };
+    }
+
+    index_current = index_next;
+  }
+
+  parameter_sets
+}
+
+/// Build an `avcC` record (the AVC decoder configuration record defined in
+/// ISO/IEC 14496-15) from H.264 parameter sets, as extradata for streams
+/// in the length-prefixed format. The record signals four byte NAL unit
+/// lengths.
+/// 
+/// # Arguments
+/// 
+/// * `sps` - SPS NAL unit bytes, including NAL unit header.
+/// * `pps` - PPS NAL unit bytes, including NAL unit header.
+pub(crate) fn avcc_from_parameter_sets(
+  sps: Sps,
+  pps: &[&[u8]],
+) -> Result<Vec<u8>> {
+  let parsed = SequenceParameterSet::parse(sps)?;
+  if pps.is_empty() || pps.len() > u8::MAX as usize {
+    return Err(Error::InvalidExtraData);
+  }
+
+  let mut record = vec![
+    0x01,
+    parsed.profile_idc,
+    parsed.constraint_set_flags,
+    parsed.level_idc,
+    // Reserved bits and NAL unit length size minus one.
+    0xfc | 0x03,
+    // Reserved bits and number of SPSs.
+    0xe0 | 0x01,
+  ];
+  push_parameter_set(&mut record, sps)?;
+  record.push(pps.len() as u8);
+  for pps in pps {
+    push_parameter_set(&mut record, pps)?;
+  }
+
+  // The high profiles also carry chroma format and bit depth.
+  if matches!(parsed.profile_idc, 100 | 110 | 122 | 144) {
+    record.extend_from_slice(&[
+      0xfc | parsed.chroma_format_idc as u8,
+      0xf8 | (parsed.bit_depth_luma - 8) as u8,
+      0xf8 | (parsed.bit_depth_chroma - 8) as u8,
+      // Number of SPS extensions.
+      0x00,
+    ]);
+  }
+
+  Ok(record)
+}
+
+/// Build an `hvcC` record (the HEVC decoder configuration record defined
+/// in ISO/IEC 14496-15) from H.265/HEVC parameter sets, as extradata for
+/// streams in the length-prefixed format. The record signals four byte
+/// NAL unit lengths. Profile, tier, level, chroma format and bit depth are
+/// taken from the first SPS.
+/// 
+/// # Arguments
+/// 
+/// * `parameter_sets` - Parameter sets of stream.
+pub(crate) fn hvcc_from_parameter_sets(
+  parameter_sets: &ParameterSetsHevc,
+) -> Result<Vec<u8>> {
+  let sps = parameter_sets.sps
+    .first()
+    .filter(|sps| sps.len() > 2 && (sps[0] >> 1) & 0x3f == 33)
+    .ok_or(Error::InvalidExtraData)?;
+
+  // Section 7.3.2.2 in the Recommendation H.265. The general profile,
+  // tier and level take up the 12 bytes after the first byte of the RBSP.
+  let rbsp = remove_emulation_prevention(&sps[2..]);
+  let mut reader = BitReader::new(&rbsp);
+  let _sps_video_parameter_set_id = reader.read_bits(4)?;
+  let max_sub_layers_minus1 = reader.read_bits(3)?;
+  let temporal_id_nesting = reader.read_bit()?;
+  let general_profile_tier_level = rbsp
+    .get(1..13)
+    .ok_or(Error::InvalidExtraData)?;
+  reader.skip_bits(96)?;
+
+  let mut sub_layer_flags = Vec::new();
+  for _ in 0..max_sub_layers_minus1 {
+    let sub_layer_profile_present = reader.read_bit()?;
+    let sub_layer_level_present = reader.read_bit()?;
+    sub_layer_flags.push((sub_layer_profile_present, sub_layer_level_present));
+  }
+  if max_sub_layers_minus1 > 0 {
+    reader.skip_bits(2 * (8 - max_sub_layers_minus1 as usize))?;
+  }
+  for (sub_layer_profile_present, sub_layer_level_present) in sub_layer_flags {
+    if sub_layer_profile_present {
+      reader.skip_bits(88)?;
+    }
+    if sub_layer_level_present {
+      reader.skip_bits(8)?;
+    }
+  }
+
+  let _sps_seq_parameter_set_id = reader.read_ue()?;
+  let chroma_format_idc = reader.read_ue()?;
+  if chroma_format_idc > 3 {
+    return Err(Error::InvalidExtraData);
+  }
+  if chroma_format_idc == 3 {
+    let _separate_colour_plane = reader.read_bit()?;
+  }
+  let _pic_width_in_luma_samples = reader.read_ue()?;
+  let _pic_height_in_luma_samples = reader.read_ue()?;
+  let conformance_window = reader.read_bit()?;
+  if conformance_window {
+    for _ in 0..4 {
+      let _conf_win_offset = reader.read_ue()?;
+    }
+  }
+  let bit_depth_luma_minus8 = reader.read_ue()?;
+  let bit_depth_chroma_minus8 = reader.read_ue()?;
+  // The record only has three bits for each bit depth.
+  if bit_depth_luma_minus8 > 7 || bit_depth_chroma_minus8 > 7 {
+    return Err(Error::InvalidExtraData);
+  }
+
+  let mut record = vec![0x01];
+  record.extend_from_slice(general_profile_tier_level);
+  record.extend_from_slice(&[
+    // Reserved bits and minimum spatial segmentation (unknown).
+    0xf0, 0x00,
+    // Reserved bits and parallelism type (unknown).
+    0xfc,
+    0xfc | chroma_format_idc as u8,
+    0xf8 | bit_depth_luma_minus8 as u8,
+    0xf8 | bit_depth_chroma_minus8 as u8,
+    // Average frame rate (unknown).
+    0x00, 0x00,
+    // Constant frame rate (unknown), number of temporal layers, temporal
+    // ID nesting and NAL unit length size minus one.
+    ((max_sub_layers_minus1 as u8 + 1) << 3) |
+      ((temporal_id_nesting as u8) << 2) |
+      0x03,
+    // Number of NAL unit arrays.
+    0x03,
+  ]);
+
+  for (nal_type, nals) in [
+    (32 /* VPS */, &parameter_sets.vps),
+    (33 /* SPS */, &parameter_sets.sps),
+    (34 /* PPS */, &parameter_sets.pps),
+  ] {
+    let num_nals = u16::try_from(nals.len())
+      .map_err(|_| Error::InvalidExtraData)?;
+    // The array is not marked complete, since the stream may also carry
+    // parameter sets in-band.
+    record.push(nal_type);
+    record.extend_from_slice(&num_nals.to_be_bytes());
+    for nal in nals {
+      push_parameter_set(&mut record, nal)?;
+    }
+  }
+
+  Ok(record)
+}
+
+/// Append a parameter set to an `avcC` or `hvcC` record, prefixed with its
+/// two byte length.
+/// 
+/// # Arguments
+/// 
+/// * `record` - Record to append to.
+/// * `nal` - Parameter set NAL unit bytes.
+fn push_parameter_set(record: &mut Vec<u8>, nal: &[u8]) -> Result<()> {
+  let size = u16::try_from(nal.len())
+    .map_err(|_| Error::InvalidExtraData)?;
+  record.extend_from_slice(&size.to_be_bytes());
+  record.extend_from_slice(nal);
+  Ok(())
+}
+
 /// The H.264 AVC spec defines a NAL start code to be either two zero
 /// bytes followed by a 0x01-byte (allowed in Annex B format) or three
 /// zeros bytes followed by a 0x01-bytes (allowed in AVCC and Annex B
@@ -148,7 +487,7 @@ fn extract_parameter_sets_from_extradata_h264_avc_annexb<'buf>(
 /// index of the first byte of the start code, and `end` is the index of
 /// the first byte after the start code. If no start code was found, it
 /// returns `None`.
-fn find_avc_start_code(
+pub(crate) fn find_avc_start_code(
   bytes: &[u8],
   offset: usize,
 ) -> Option<(usize, usize)> {
@@ -1,6 +1,11 @@
 extern crate ffmpeg_next as ffmpeg;
 
 use std::ptr;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 
 use std::ffi::{CString, CStr, c_void};
 use std::os::raw::{c_char, c_int};
@@ -9,16 +14,30 @@ use std::mem::transmute;
 use tracing::Level;
 
 #[cfg(feature = "ndarray")]
-use ndarray::Array3;
+use ndarray::{Array2, Array3};
 
 use ffmpeg::{
+  Dictionary,
   Error,
   Rational,
 };
-use ffmpeg::format::context::Output;
+use ffmpeg::format::context::{Input, Output};
+use ffmpeg::codec::Parameters;
+use ffmpeg::codec::packet::{
+  Packet,
+  Ref as PacketRef,
+  Mut as PacketMut,
+  side_data::Type as PacketSideDataType,
+};
 use ffmpeg::encoder::video::Video;
+use ffmpeg::software::resampling::context::Context as Resampler;
 use ffmpeg::util::frame::video::Video as Frame;
+use ffmpeg::util::frame::audio::Audio as AudioFrame;
+use ffmpeg::util::format::Sample;
 #[cfg(feature = "ndarray")]
+use ffmpeg::util::format::sample::Type as SampleType;
+use ffmpeg::util::error::{ENOMEM, EIO};
+use ffmpeg::ChannelLayout;
 use ffmpeg::util::format::Pixel;
 
 use ffmpeg::ffi::*;
@@ -60,6 +79,253 @@ pub fn output_raw(format: &str) -> Result<Output, Error> {
   }
 }
 
+/// Backs the `AVIOInterruptCB` of an input. The backend periodically polls
+/// it during blocking operations and aborts them with `AVERROR_EXIT` when it
+/// has been triggered, either by cancellation or because the current
+/// deadline has passed.
+/// 
+/// The interrupt must stay at the same address and outlive the `Input` it
+/// was installed on.
+pub struct Interrupt {
+  cancelled: Arc<AtomicBool>,
+  deadline: Mutex<Option<Instant>>,
+}
+
+impl Interrupt {
+
+  /// Create a new interrupt that is triggered when `cancelled` is set.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `cancelled` - Shared cancellation flag.
+  pub fn new(cancelled: Arc<AtomicBool>) -> Self {
+    Self {
+      cancelled,
+      deadline: Mutex::new(None),
+    }
+  }
+
+  /// Set or clear the deadline for the current operation.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `deadline` - Point in time after which to interrupt, or `None`.
+  pub fn set_deadline(&self, deadline: Option<Instant>) {
+    *self.deadline.lock().unwrap() = deadline;
+  }
+
+  /// Whether or not the interrupt has been triggered by cancellation.
+  pub fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::SeqCst)
+  }
+
+  /// Whether or not the interrupt has been triggered.
+  pub fn is_triggered(&self) -> bool {
+    self.cancelled.load(Ordering::SeqCst) ||
+    self.deadline
+      .lock()
+      .unwrap()
+      .map(|deadline| Instant::now() >= deadline)
+      .unwrap_or(false)
+  }
+
+}
+
+extern "C" fn interrupt_callback(opaque: *mut c_void) -> c_int {
+  let interrupt = unsafe { &*(opaque as *const Interrupt) };
+  interrupt.is_triggered() as c_int
+}
+
+/// Install an interrupt as interrupt callback on a format context.
+/// 
+/// # Arguments
+/// 
+/// * `context` - Format context to install callback on.
+/// * `interrupt` - Interrupt to poll.
+unsafe fn install_interrupt(
+  context: *mut AVFormatContext,
+  interrupt: &Interrupt,
+) {
+  (*context).interrupt_callback = AVIOInterruptCB {
+    callback: Some(interrupt_callback),
+    opaque: interrupt as *const Interrupt as *mut c_void,
+  };
+}
+
+/// This function is similar to `input_with_dictionary` in ffmpeg-next, but
+/// installs an interrupt callback before opening the input, so that opening
+/// as well as any subsequent reads can be interrupted.
+/// 
+/// The interrupt must outlive the returned `Input`.
+/// 
+/// # Arguments
+/// 
+/// * `path` - Path or URL to open.
+/// * `options` - Options to pass on to `avformat_open_input`.
+/// * `interrupt` - Interrupt to install.
+pub fn input_with_interrupt(
+  path: &Path,
+  options: Dictionary,
+  interrupt: &Interrupt,
+) -> Result<Input, Error> {
+  let path = CString::new(path.as_os_str().to_str().unwrap()).unwrap();
+
+  unsafe {
+    let mut input_ptr = avformat_alloc_context();
+    if input_ptr.is_null() {
+      return Err(Error::Other { errno: ENOMEM });
+    }
+
+    install_interrupt(input_ptr, interrupt);
+
+    let mut options = options.disown();
+    let ret = avformat_open_input(
+      &mut input_ptr,
+      path.as_ptr(),
+      ptr::null_mut(),
+      &mut options);
+    Dictionary::own(options);
+
+    // On failure, `avformat_open_input` frees the format context.
+    if ret < 0 {
+      return Err(Error::from(ret));
+    }
+
+    match avformat_find_stream_info(input_ptr, ptr::null_mut()) {
+      ret if ret >= 0 => {
+        Ok(Input::wrap(input_ptr))
+      },
+      e => {
+        avformat_close_input(&mut input_ptr);
+        Err(Error::from(e))
+      },
+    }
+  }
+}
+
+/// Any type that can be read from and seeked in, and can be sent to
+/// another thread. Used as the backing source for custom input.
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Holds the custom IO context of an input opened with `input_custom`,
+/// as well as the reader that backs it.
+/// 
+/// This must be dropped after the corresponding `Input`, since the input
+/// will keep using the IO context until it is closed.
+pub struct CustomInputIo {
+  io: *mut AVIOContext,
+  _reader: Box<Box<dyn ReadSeek>>,
+}
+
+impl Drop for CustomInputIo {
+
+  fn drop(&mut self) {
+    unsafe {
+      // The buffer might have been reallocated by `libavformat`, so we free
+      // the one that the IO context currently points to.
+      av_freep((&mut (*self.io).buffer) as *mut *mut u8 as *mut c_void);
+      avio_context_free(&mut self.io);
+    }
+  }
+
+}
+
+unsafe impl Send for CustomInputIo {}
+
+/// This function is similar to the existing bindings in ffmpeg-next like
+/// `input` and `input_with_dictionary`, but instead of opening a file or
+/// URL, it reads from any type that implements `Read` and `Seek`.
+/// 
+/// The returned `CustomInputIo` and the interrupt must outlive the returned
+/// `Input`.
+/// 
+/// # Arguments
+/// 
+/// * `reader` - Reader to read from.
+/// * `options` - Options to pass on to `avformat_open_input`.
+/// * `interrupt` - Interrupt to install.
+pub fn input_custom(
+  reader: Box<dyn ReadSeek>,
+  options: Dictionary,
+  interrupt: &Interrupt,
+) -> Result<(Input, CustomInputIo), Error> {
+  // Size of the IO buffer. This is the same size `libavformat` uses for
+  // its own file IO.
+  const BUFFER_SIZE: usize = 32768;
+
+  unsafe {
+    // Double boxing gives us a thin pointer to pass on as `opaque`. The
+    // inner box lives on the heap so moving the outer box is fine.
+    let mut reader = Box::new(reader);
+    let opaque = (&mut *reader) as *mut Box<dyn ReadSeek> as *mut c_void;
+
+    let buffer = av_malloc(BUFFER_SIZE) as *mut u8;
+    if buffer.is_null() {
+      return Err(Error::Other { errno: ENOMEM });
+    }
+
+    // Create a custom IO context around our buffer.
+    let io: *mut AVIOContext =
+      avio_alloc_context(
+        buffer,
+        BUFFER_SIZE as c_int,
+        // Set stream to READ.
+        0,
+        opaque,
+        Some(input_custom_read_callback),
+        // No `write_packet`.
+        None,
+        Some(input_custom_seek_callback),
+      );
+
+    if io.is_null() {
+      av_free(buffer as *mut c_void);
+      return Err(Error::Other { errno: ENOMEM });
+    }
+
+    // From here on the IO context is cleaned up by `CustomInputIo`.
+    let custom_io = CustomInputIo {
+      io,
+      _reader: reader,
+    };
+
+    let mut input_ptr = avformat_alloc_context();
+    if input_ptr.is_null() {
+      return Err(Error::Other { errno: ENOMEM });
+    }
+
+    // Note: `avformat_open_input` sets `AVFMT_FLAG_CUSTOM_IO` itself when
+    // `pb` is set, so it will not try to close our IO context.
+    (*input_ptr).pb = io;
+    install_interrupt(input_ptr, interrupt);
+
+    let mut options = options.disown();
+    let ret = avformat_open_input(
+      &mut input_ptr,
+      ptr::null(),
+      ptr::null_mut(),
+      &mut options);
+    Dictionary::own(options);
+
+    // On failure, `avformat_open_input` frees the format context.
+    if ret < 0 {
+      return Err(Error::from(ret));
+    }
+
+    match avformat_find_stream_info(input_ptr, ptr::null_mut()) {
+      ret if ret >= 0 => {
+        Ok((Input::wrap(input_ptr), custom_io))
+      },
+      e => {
+        avformat_close_input(&mut input_ptr);
+        Err(Error::from(e))
+      },
+    }
+  }
+}
+
 /// This function initializes a dynamic buffer and inserts it into an
 /// output context to allow a write to happen. Afterwards, the callee
 /// can use `output_raw_buf_end` to retrieve what was written.
@@ -231,6 +497,37 @@ pub fn get_encoder_time_base(encoder: &Video) -> Rational {
   }
 }
 
+/// Get the fixed quality scale of an encoder, which is set through the
+/// `qscale` flag and `global_quality` option. Returns `None` if the encoder
+/// does not use a fixed quality scale.
+/// 
+/// # Arguments
+/// 
+/// * `encoder` - Encoder to get fixed quality scale of.
+pub fn get_encoder_fixed_quality(encoder: &Video) -> Option<i32> {
+  unsafe {
+    let context = encoder.0.as_ptr();
+    if (*context).flags & AV_CODEC_FLAG_QSCALE as c_int != 0 {
+      Some((*context).global_quality)
+    } else {
+      None
+    }
+  }
+}
+
+/// Set the `quality` field of a frame. (Not natively supported in the
+/// public API.)
+/// 
+/// # Arguments
+/// 
+/// * `frame` - Frame to set quality of.
+/// * `quality` - Quality scale multiplied by `FF_QP2LAMBDA`.
+pub fn set_frame_quality(frame: &mut Frame, quality: i32) {
+  unsafe {
+    (*frame.as_mut_ptr()).quality = quality;
+  }
+}
+
 /// Copy frame properties from `src` to `dst`.
 /// 
 /// # Arguments
@@ -243,6 +540,261 @@ pub fn copy_frame_props(src: &Frame, dst: &mut Frame) {
   }
 }
 
+/// Get the upper bound on the number of samples the resampler will output
+/// for the next conversion with `num_input_samples` input samples.
+/// 
+/// # Arguments
+/// 
+/// * `resampler` - Resampler to query.
+/// * `num_input_samples` - Number of samples that will be fed.
+pub fn get_resampler_out_samples(
+  resampler: &mut Resampler,
+  num_input_samples: usize,
+) -> usize {
+  unsafe {
+    swr_get_out_samples(
+      resampler.as_mut_ptr(),
+      num_input_samples as c_int,
+    ).max(0) as usize
+  }
+}
+
+/// Wrapper around `AVAudioFifo`, a first-in first-out buffer for audio
+/// samples. It is used to cut audio into frames of the size a particular
+/// audio encoder expects.
+pub struct AudioFifo {
+  ptr: *mut AVAudioFifo,
+  format: Sample,
+  channels: u16,
+}
+
+impl AudioFifo {
+
+  /// Allocate a new audio FIFO.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `format` - Sample format of the samples in the FIFO.
+  /// * `channels` - Number of channels.
+  pub fn new(
+    format: Sample,
+    channels: u16,
+  ) -> Result<Self, Error> {
+    unsafe {
+      // Initial size, the FIFO will grow automatically.
+      const INITIAL_SIZE: c_int = 1024;
+
+      let ptr = av_audio_fifo_alloc(
+        format.into(),
+        channels as c_int,
+        INITIAL_SIZE,
+      );
+
+      if !ptr.is_null() {
+        Ok(Self {
+          ptr,
+          format,
+          channels,
+        })
+      } else {
+        Err(Error::Other { errno: ENOMEM })
+      }
+    }
+  }
+
+  /// Number of samples currently in the FIFO.
+  pub fn size(&self) -> usize {
+    unsafe {
+      av_audio_fifo_size(self.ptr).max(0) as usize
+    }
+  }
+
+  /// Write all samples in `frame` to the FIFO. The frame must have the
+  /// same sample format and number of channels as the FIFO.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame` - Frame to take samples from.
+  pub fn write(&mut self, frame: &AudioFrame) -> Result<(), Error> {
+    unsafe {
+      let frame_ptr = frame.as_ptr();
+      let ret = av_audio_fifo_write(
+        self.ptr,
+        (*frame_ptr).extended_data as *mut *mut c_void,
+        (*frame_ptr).nb_samples,
+      );
+
+      if ret >= 0 {
+        Ok(())
+      } else {
+        Err(Error::from(ret))
+      }
+    }
+  }
+
+  /// Read up to `num_samples` samples from the FIFO into a new frame.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `num_samples` - Maximum number of samples to read.
+  /// * `channel_layout` - Channel layout to set on the frame.
+  pub fn read(
+    &mut self,
+    num_samples: usize,
+    channel_layout: ChannelLayout,
+  ) -> Result<AudioFrame, Error> {
+    let num_samples = num_samples.min(self.size());
+    let mut frame = AudioFrame::new(
+      self.format,
+      num_samples,
+      channel_layout,
+    );
+    frame.set_channels(self.channels);
+
+    unsafe {
+      let frame_ptr = frame.as_mut_ptr();
+      let ret = av_audio_fifo_read(
+        self.ptr,
+        (*frame_ptr).extended_data as *mut *mut c_void,
+        num_samples as c_int,
+      );
+
+      if ret >= 0 {
+        (*frame_ptr).nb_samples = ret;
+        Ok(frame)
+      } else {
+        Err(Error::from(ret))
+      }
+    }
+  }
+
+}
+
+impl Drop for AudioFifo {
+
+  fn drop(&mut self) {
+    unsafe {
+      av_audio_fifo_free(self.ptr);
+    }
+  }
+
+}
+
+unsafe impl Send for AudioFifo {}
+
+/// Wrapper around `AVBSFContext`, a (chain of) bitstream filter(s). Not
+/// supported in the public API.
+pub struct BitstreamFilterContext {
+  ptr: *mut AVBSFContext,
+}
+
+impl BitstreamFilterContext {
+
+  /// Parse and initialize a bitstream filter chain.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `filters` - Comma separated list of filters with options, like
+  ///   `h264_metadata=aud=insert,dump_extra`. An empty string produces a
+  ///   filter that passes packets through unchanged.
+  /// * `parameters` - Codec parameters of the input stream.
+  /// * `time_base` - Time base of the input stream.
+  pub fn new(
+    filters: &str,
+    parameters: &Parameters,
+    time_base: Rational,
+  ) -> Result<Self, Error> {
+    let filters = CString::new(filters).map_err(|_| Error::InvalidData)?;
+
+    unsafe {
+      let mut ptr: *mut AVBSFContext = ptr::null_mut();
+      let ret = av_bsf_list_parse_str(filters.as_ptr(), &mut ptr);
+      if ret < 0 {
+        return Err(Error::from(ret));
+      }
+
+      // Take ownership right away, so that the context is freed on error.
+      let context = Self { ptr };
+
+      let ret = avcodec_parameters_copy((*ptr).par_in, parameters.as_ptr());
+      if ret < 0 {
+        return Err(Error::from(ret));
+      }
+      (*ptr).time_base_in = time_base.into();
+
+      match av_bsf_init(ptr) {
+        0 => Ok(context),
+        e => Err(Error::from(e)),
+      }
+    }
+  }
+
+  /// Get the codec parameters of the output stream.
+  pub fn parameters_out(&self) -> Parameters {
+    let mut parameters = Parameters::new();
+    unsafe {
+      avcodec_parameters_copy(parameters.as_mut_ptr(), (*self.ptr).par_out);
+    }
+    parameters
+  }
+
+  /// Get the time base of the output stream.
+  pub fn time_base_out(&self) -> Rational {
+    unsafe {
+      Rational::from((*self.ptr).time_base_out)
+    }
+  }
+
+  /// Send a packet to the filter. The filter takes ownership of the packet
+  /// contents, leaving `packet` blank. Sending `None` signals the end of
+  /// the stream, after which remaining packets can be received.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `packet` - Packet to filter, or `None` to flush.
+  pub fn send(&mut self, packet: Option<&mut Packet>) -> Result<(), Error> {
+    unsafe {
+      let packet_ptr = match packet {
+        Some(packet) => packet.as_mut_ptr(),
+        None => ptr::null_mut(),
+      };
+
+      match av_bsf_send_packet(self.ptr, packet_ptr) {
+        0 => Ok(()),
+        e => Err(Error::from(e)),
+      }
+    }
+  }
+
+  /// Receive a filtered packet. Returns `EAGAIN` if more input is needed,
+  /// and `Eof` if the filter was flushed and has no more packets.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `packet` - Packet to receive into.
+  pub fn receive(&mut self, packet: &mut Packet) -> Result<(), Error> {
+    unsafe {
+      match av_bsf_receive_packet(self.ptr, packet.as_mut_ptr()) {
+        0 => Ok(()),
+        e => Err(Error::from(e)),
+      }
+    }
+  }
+
+}
+
+impl Drop for BitstreamFilterContext {
+
+  fn drop(&mut self) {
+    unsafe {
+      av_bsf_free(&mut self.ptr);
+    }
+  }
+
+}
+
+unsafe impl Send for BitstreamFilterContext {}
+
 /// A frame array is the `ndarray` version of `AVFrame`. It is 3-dimensional
 /// array with dims `(H, W, C)` and type byte.
 #[cfg(feature = "ndarray")]
@@ -351,6 +903,89 @@ pub fn convert_frame_to_ndarray_rgb24(
   }
 }
 
+/// An audio frame array is the `ndarray` version of an audio `AVFrame`. It
+/// is a 2-dimensional array with dims `(S, C)` (samples and channels) and
+/// type `f32`.
+#[cfg(feature = "ndarray")]
+pub type AudioFrameArray = Array2<f32>;
+
+/// Converts an interleaved `f32` audio `AVFrame` produced by ffmpeg to an
+/// `ndarray`.
+/// 
+/// # Arguments
+/// 
+/// * `frame` - Audio frame to convert.
+/// 
+/// # Returns
+/// 
+/// A two-dimensional `ndarray` with dimensions `(S, C)` and type `f32`.
+#[cfg(feature = "ndarray")]
+pub fn convert_audio_frame_to_ndarray_f32(
+  frame: &AudioFrame,
+) -> Result<AudioFrameArray, Error> {
+  unsafe {
+    let frame_ptr = frame.as_ptr();
+    let frame_format = transmute::<c_int, AVSampleFormat>((*frame_ptr).format);
+    assert_eq!(frame_format, AVSampleFormat::AV_SAMPLE_FMT_FLT);
+
+    let num_samples = (*frame_ptr).nb_samples as usize;
+    let num_channels = frame.channels() as usize;
+
+    // Packed formats store all channels interleaved in the first plane,
+    // which maps directly onto a standard layout `(S, C)` array.
+    let samples = std::slice::from_raw_parts(
+      (*frame_ptr).data[0] as *const f32,
+      num_samples * num_channels,
+    );
+
+    AudioFrameArray::from_shape_vec(
+        (num_samples, num_channels),
+        samples.to_vec())
+      .map_err(|_| Error::InvalidData)
+  }
+}
+
+/// Converts an `ndarray` with dimensions `(S, C)` to an interleaved `f32`
+/// audio `AVFrame` for ffmpeg.
+/// 
+/// # Arguments
+/// 
+/// * `frame_array` - Audio samples to convert. The array format must be
+///   `(S, C)`.
+/// * `sample_rate` - Sample rate of the samples.
+/// 
+/// # Returns
+/// 
+/// An ffmpeg-native audio `AvFrame`.
+#[cfg(feature = "ndarray")]
+pub fn convert_ndarray_to_audio_frame_f32(
+  frame_array: &AudioFrameArray,
+  sample_rate: u32,
+) -> Result<AudioFrame, Error> {
+  assert!(frame_array.is_standard_layout());
+
+  let (num_samples, num_channels) = frame_array.dim();
+
+  let mut frame = AudioFrame::new(
+    Sample::F32(SampleType::Packed),
+    num_samples,
+    ChannelLayout::default(num_channels as i32));
+  frame.set_channels(num_channels as u16);
+  frame.set_rate(sample_rate);
+
+  unsafe {
+    // Packed formats store all channels interleaved in the first plane,
+    // so we can copy the array as-is.
+    std::ptr::copy_nonoverlapping(
+      frame_array.as_ptr(),
+      (*frame.as_mut_ptr()).data[0] as *mut f32,
+      num_samples * num_channels,
+    );
+  }
+
+  Ok(frame)
+}
+
 /// Retrieve a reference to the extradata bytes in codec parameters of
 /// an output stream.
 /// 
@@ -368,13 +1003,288 @@ pub fn extradata<'params>(
     .ok_or_else(|| Error::StreamNotFound)?;
 
   Ok(unsafe {
-    std::slice::from_raw_parts(
-      (*parameters.as_ptr()).extradata,
-      (*parameters.as_ptr()).extradata_size as usize,
-    )
+    extradata_slice(parameters.as_ptr())
   })
 }
 
+/// Set a metadata tag on an output format context. (The public API can
+/// only replace all metadata at once.)
+/// 
+/// # Arguments
+/// 
+/// * `output` - Output to set metadata on.
+/// * `key` - Tag name.
+/// * `value` - Tag value.
+pub fn set_output_metadata(
+  output: &mut Output,
+  key: &str,
+  value: &str,
+) -> Result<(), Error> {
+  let key = CString::new(key).unwrap();
+  let value = CString::new(value).unwrap();
+
+  unsafe {
+    match av_dict_set(
+      &mut (*output.as_mut_ptr()).metadata,
+      key.as_ptr(),
+      value.as_ptr(),
+      0,
+    ) {
+      0 => Ok(()),
+      e => Err(Error::from(e)),
+    }
+  }
+}
+
+/// Get the bit rate from codec parameters. (Not natively supported in the
+/// public API.)
+/// 
+/// # Arguments
+/// 
+/// * `parameters` - Codec parameters to get bit rate of.
+pub fn codec_parameters_bit_rate(parameters: &Parameters) -> i64 {
+  unsafe {
+    (*parameters.as_ptr()).bit_rate
+  }
+}
+
+/// Get the extradata from codec parameters. Returns an empty slice if the
+/// codec parameters have no extradata.
+/// 
+/// # Arguments
+/// 
+/// * `parameters` - Codec parameters to get extradata of.
+pub fn codec_parameters_extradata(parameters: &Parameters) -> &[u8] {
+  unsafe {
+    extradata_slice(parameters.as_ptr())
+  }
+}
+
+/// Replace the extradata in codec parameters. (Not natively supported in
+/// the public API.)
+///
+/// # Arguments
+///
+/// * `parameters` - Codec parameters to set extradata of.
+/// * `extradata` - New extradata bytes.
+pub fn set_codec_parameters_extradata(
+  parameters: &mut Parameters,
+  extradata: &[u8],
+) -> Result<(), Error> {
+  unsafe {
+    let parameters = parameters.as_mut_ptr();
+    av_freep((&mut (*parameters).extradata) as *mut *mut u8 as *mut c_void);
+    (*parameters).extradata_size = 0;
+
+    // The backend requires extradata to be followed by zeroed padding.
+    let buffer = av_mallocz(
+      extradata.len() + AV_INPUT_BUFFER_PADDING_SIZE as usize) as *mut u8;
+    if buffer.is_null() {
+      return Err(Error::Other { errno: ENOMEM });
+    }
+
+    ptr::copy_nonoverlapping(extradata.as_ptr(), buffer, extradata.len());
+    (*parameters).extradata = buffer;
+    (*parameters).extradata_size = extradata.len() as c_int;
+  }
+
+  Ok(())
+}
+
+/// Borrow the extradata of raw codec parameters as slice. The caller must
+/// make sure the slice does not outlive the codec parameters.
+/// 
+/// # Arguments
+/// 
+/// * `parameters` - Pointer to codec parameters.
+unsafe fn extradata_slice<'params>(
+  parameters: *const AVCodecParameters,
+) -> &'params [u8] {
+  let extradata = (*parameters).extradata;
+  let extradata_size = (*parameters).extradata_size;
+  if !extradata.is_null() && extradata_size > 0 {
+    std::slice::from_raw_parts(extradata, extradata_size as usize)
+  } else {
+    &[]
+  }
+}
+
+/// Get the profile and level from codec parameters. Both are negative if
+/// unknown. (Not natively supported in the public API.)
+/// 
+/// # Arguments
+/// 
+/// * `parameters` - Codec parameters to get profile and level of.
+pub fn codec_parameters_profile_and_level(parameters: &Parameters) -> (i32, i32) {
+  unsafe {
+    (
+      (*parameters.as_ptr()).profile,
+      (*parameters.as_ptr()).level,
+    )
+  }
+}
+
+/// Get the codec tag (FourCC) from codec parameters.
+/// 
+/// # Arguments
+/// 
+/// * `parameters` - Codec parameters to get codec tag of.
+pub fn codec_parameters_codec_tag(parameters: &Parameters) -> u32 {
+  unsafe {
+    (*parameters.as_ptr()).codec_tag
+  }
+}
+
+/// Get the width and height from video codec parameters.
+/// 
+/// # Arguments
+/// 
+/// * `parameters` - Codec parameters to get dimensions of.
+pub fn codec_parameters_dimensions(parameters: &Parameters) -> (u32, u32) {
+  unsafe {
+    (
+      (*parameters.as_ptr()).width.max(0) as u32,
+      (*parameters.as_ptr()).height.max(0) as u32,
+    )
+  }
+}
+
+/// Get the luma bit depth of video codec parameters from the pixel format.
+/// Returns `None` if the pixel format is unknown.
+/// 
+/// # Arguments
+/// 
+/// * `parameters` - Codec parameters to get bit depth of.
+pub fn codec_parameters_bit_depth(parameters: &Parameters) -> Option<u32> {
+  unsafe {
+    let format = (*parameters.as_ptr()).format;
+    if format < 0 {
+      return None;
+    }
+
+    let descriptor = av_pix_fmt_desc_get(
+      transmute::<c_int, AVPixelFormat>(format));
+    if !descriptor.is_null() {
+      Some((*descriptor).comp[0].depth as u32)
+    } else {
+      None
+    }
+  }
+}
+
+/// Get the pixel format from video codec parameters.
+/// 
+/// # Arguments
+/// 
+/// * `parameters` - Codec parameters to get pixel format of.
+pub fn codec_parameters_pixel_format(parameters: &Parameters) -> Pixel {
+  unsafe {
+    let format = (*parameters.as_ptr()).format;
+    if format < 0 {
+      return Pixel::None;
+    }
+
+    transmute::<c_int, AVPixelFormat>(format).into()
+  }
+}
+
+/// Get the sample rate, number of channels and sample format from audio
+/// codec parameters.
+/// 
+/// # Arguments
+/// 
+/// * `parameters` - Codec parameters to get audio properties of.
+pub fn codec_parameters_audio(parameters: &Parameters) -> (u32, u16, Sample) {
+  unsafe {
+    let parameters = parameters.as_ptr();
+    let format = (*parameters).format;
+    let sample_format = if format >= 0 {
+      transmute::<c_int, AVSampleFormat>(format).into()
+    } else {
+      Sample::None
+    };
+
+    (
+      (*parameters).sample_rate.max(0) as u32,
+      (*parameters).channels.max(0) as u16,
+      sample_format,
+    )
+  }
+}
+
+/// Copy the properties (timestamps, flags, stream index, side data, etc.)
+/// but not the payload of one packet to another. (Not natively supported
+/// in the public API.)
+/// 
+/// # Arguments
+/// 
+/// * `dst` - Packet to copy properties to.
+/// * `src` - Packet to copy properties from.
+pub fn packet_copy_props(dst: &mut Packet, src: &Packet) -> Result<(), Error> {
+  unsafe {
+    match av_packet_copy_props(dst.as_mut_ptr(), src.as_ptr()) {
+      0 => Ok(()),
+      e => Err(Error::from(e)),
+    }
+  }
+}
+
+/// Get the side data of a packet as pairs of type and raw bytes. (The
+/// public API does not allow borrowing the bytes for the lifetime of the
+/// packet.)
+/// 
+/// # Arguments
+/// 
+/// * `packet` - Packet to get side data of.
+pub fn packet_side_data(packet: &Packet) -> Vec<(PacketSideDataType, &[u8])> {
+  unsafe {
+    let packet_ptr = packet.as_ptr();
+    let side_data_elems = (*packet_ptr).side_data_elems.max(0) as usize;
+    (0..side_data_elems)
+      .map(|i| {
+        let side_data = (*packet_ptr).side_data.add(i);
+        let data = if !(*side_data).data.is_null() {
+          std::slice::from_raw_parts(
+            (*side_data).data,
+            (*side_data).size as usize)
+        } else {
+          &[]
+        };
+        (PacketSideDataType::from((*side_data).type_), data)
+      })
+      .collect()
+  }
+}
+
+/// Get the rotation in degrees (counterclockwise) described by a display
+/// matrix, as found in stream side data. Returns `None` if the matrix is
+/// malformed or does not describe a rotation.
+/// 
+/// # Arguments
+/// 
+/// * `matrix` - Raw display matrix bytes (nine 32-bit integers).
+pub fn display_matrix_rotation(matrix: &[u8]) -> Option<f64> {
+  if matrix.len() < 9 * std::mem::size_of::<i32>() {
+    return None;
+  }
+
+  let matrix = matrix
+    .chunks_exact(4)
+    .take(9)
+    .map(|bytes| i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    .collect::<Vec<_>>();
+
+  let rotation = unsafe {
+    av_display_rotation_get(matrix.as_ptr())
+  };
+
+  if rotation.is_nan() {
+    None
+  } else {
+    Some(rotation)
+  }
+}
+
 /// Whether or not the output format context is configured to use H.264
 /// packetization mode 0.
 /// 
@@ -481,6 +1391,70 @@ extern "C" fn output_raw_buf_start_callback(
   buffer_size
 }
 
+/// Passthrough function that is passed to `libavformat` in `avio_alloc_context`
+/// and reads from the reader held in `opaque`.
+extern "C" fn input_custom_read_callback(
+  opaque: *mut c_void,
+  buffer: *mut u8,
+  buffer_size: c_int,
+) -> c_int {
+  unsafe {
+    let reader = &mut *(opaque as *mut Box<dyn ReadSeek>);
+    let buffer = std::slice::from_raw_parts_mut(
+      buffer,
+      buffer_size as usize,
+    );
+
+    match reader.read(buffer) {
+      Ok(0) => AVERROR_EOF,
+      Ok(num_bytes) => num_bytes as c_int,
+      Err(_) => AVERROR(EIO),
+    }
+  }
+}
+
+/// Passthrough function that is passed to `libavformat` in `avio_alloc_context`
+/// and seeks in the reader held in `opaque`.
+extern "C" fn input_custom_seek_callback(
+  opaque: *mut c_void,
+  offset: i64,
+  whence: c_int,
+) -> i64 {
+  unsafe {
+    let reader = &mut *(opaque as *mut Box<dyn ReadSeek>);
+    // We can always seek, so we don't care about `AVSEEK_FORCE`.
+    let whence = whence & !(AVSEEK_FORCE as c_int);
+
+    // `libavformat` asks for the size of the stream with `AVSEEK_SIZE`. We
+    // find out by seeking to the end and back.
+    if whence == AVSEEK_SIZE as c_int {
+      let size = reader
+        .stream_position()
+        .and_then(|position| {
+          let size = reader.seek(SeekFrom::End(0))?;
+          reader.seek(SeekFrom::Start(position))?;
+          Ok(size)
+        });
+
+      return size
+        .map(|size| size as i64)
+        .unwrap_or(AVERROR(EIO) as i64);
+    }
+
+    let position = match whence {
+      0 /* SEEK_SET */ => SeekFrom::Start(offset as u64),
+      1 /* SEEK_CUR */ => SeekFrom::Current(offset),
+      2 /* SEEK_END */ => SeekFrom::End(offset),
+      _ => return AVERROR(EIO) as i64,
+    };
+
+    reader
+      .seek(position)
+      .map(|position| position as i64)
+      .unwrap_or(AVERROR(EIO) as i64)
+  }
+}
+
 /// Internal function with C-style callback behavior that receives all log
 /// messages from ffmpeg and handles them with the `log` crate, the Rust way.
 /// 
@@ -2,15 +2,30 @@ extern crate ffmpeg_next as ffmpeg;
 
 use ffmpeg::util::{
   frame::Video as AvFrame,
+  frame::Audio as AvAudioFrame,
   format::Pixel as AvPixel,
+  format::sample::{
+    Sample as AvSample,
+    Type as AvSampleType,
+  },
 };
 
 /// Re-export internal `AvFrame` for caller to use.
 pub type RawFrame = AvFrame;
 
+/// Re-export internal `AvAudioFrame` for caller to use.
+pub type RawAudioFrame = AvAudioFrame;
+
 /// Re-export frame type as ndarray.
 #[cfg(feature = "ndarray")]
 pub type Frame = super::ffi::FrameArray;
 
+/// Re-export audio frame type as ndarray.
+#[cfg(feature = "ndarray")]
+pub type AudioFrame = super::ffi::AudioFrameArray;
+
 /// Default frame pixel format.
-pub(crate) const FRAME_PIXEL_FORMAT: AvPixel = AvPixel::RGB24;
\ No newline at end of file
+pub(crate) const FRAME_PIXEL_FORMAT: AvPixel = AvPixel::RGB24;
+
+/// Default audio frame sample format (interleaved 32-bit float).
+pub(crate) const AUDIO_FRAME_SAMPLE_FORMAT: AvSample = AvSample::F32(AvSampleType::Packed);
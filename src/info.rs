@@ -0,0 +1,239 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::collections::HashMap;
+
+use ffmpeg::{
+  ffi::{AV_NOPTS_VALUE, AV_TIME_BASE_Q},
+  codec::packet::side_data::Type as AvSideDataType,
+  format::stream::Stream as AvStream,
+  media::Type as AvMediaType,
+  util::{
+    dictionary::Ref as AvDictionaryRef,
+    format::pixel::Pixel as AvPixel,
+    format::sample::Sample as AvSample,
+  },
+  Rational as AvRational,
+};
+
+use super::{
+  io::Reader,
+  Error,
+  Time,
+  ffi::{
+    display_matrix_rotation,
+    codec_parameters_audio,
+    codec_parameters_bit_rate,
+    codec_parameters_dimensions,
+    codec_parameters_pixel_format,
+  },
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Describes the container and all streams of a media source. This can
+/// be used to inspect and validate a source without going through the
+/// backend.
+#[derive(Clone, Debug)]
+pub struct MediaInfo {
+  /// Short name of the container format, like "mov,mp4,m4a,3gp,3g2,mj2".
+  pub format_name: String,
+  /// Descriptive name of the container format.
+  pub format_long_name: String,
+  /// Duration of the media. Has no value if the container does not know.
+  pub duration: Time,
+  /// Total bit rate in bits per second, if known.
+  pub bit_rate: Option<usize>,
+  /// Container-level metadata tags.
+  pub tags: HashMap<String, String>,
+  /// Information about each stream, in order of stream index.
+  pub streams: Vec<StreamMediaInfo>,
+}
+
+impl MediaInfo {
+
+  /// Fetch media information from a reader.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `reader` - Reader to inspect.
+  pub(crate) fn from_reader(reader: &Reader) -> Result<Self> {
+    let format = reader.input.format();
+
+    let streams = reader
+      .input
+      .streams()
+      .map(|stream| StreamMediaInfo::from_stream(&stream))
+      .collect::<Result<Vec<_>>>()?;
+
+    Ok(Self {
+      format_name: format.name().to_string(),
+      format_long_name: format.description().to_string(),
+      duration: timestamp(reader.input.duration(), AV_TIME_BASE_Q.into()),
+      bit_rate: positive(reader.input.bit_rate()),
+      tags: tags(reader.input.metadata()),
+      streams,
+    })
+  }
+
+  /// Iterate over all video streams.
+  pub fn video_streams(&self) -> impl Iterator<Item = &StreamMediaInfo> {
+    self.streams
+      .iter()
+      .filter(|stream| matches!(stream.kind, StreamKind::Video { .. }))
+  }
+
+  /// Iterate over all audio streams.
+  pub fn audio_streams(&self) -> impl Iterator<Item = &StreamMediaInfo> {
+    self.streams
+      .iter()
+      .filter(|stream| matches!(stream.kind, StreamKind::Audio { .. }))
+  }
+
+}
+
+/// Describes a single stream in a media source.
+#[derive(Clone, Debug)]
+pub struct StreamMediaInfo {
+  /// Stream index.
+  pub index: usize,
+  /// Name of the codec, like "h264" or "aac".
+  pub codec_name: String,
+  /// Start time of the stream. Has no value if unknown.
+  pub start_time: Time,
+  /// Duration of the stream. Has no value if unknown.
+  pub duration: Time,
+  /// Bit rate in bits per second, if known.
+  pub bit_rate: Option<usize>,
+  /// Stream-level metadata tags.
+  pub tags: HashMap<String, String>,
+  /// Media specific stream information.
+  pub kind: StreamKind,
+}
+
+impl StreamMediaInfo {
+
+  /// Fetch stream media information from a backend stream. This only looks
+  /// at the codec parameters of the stream and does not open a decoder, so
+  /// it also works for streams without a decoder in the backend.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `stream` - Stream to inspect.
+  fn from_stream(stream: &AvStream) -> Result<Self> {
+    let parameters = stream.parameters();
+    let time_base = stream.time_base();
+
+    let kind = match parameters.medium() {
+      AvMediaType::Video => {
+        let (width, height) = codec_parameters_dimensions(&parameters);
+
+        let frame_rate = [stream.avg_frame_rate(), stream.rate()]
+          .into_iter()
+          .find(|rate| rate.numerator() > 0 && rate.denominator() > 0)
+          .map(|rate| (rate.numerator(), rate.denominator()));
+
+        let pixel_format = match codec_parameters_pixel_format(&parameters) {
+          AvPixel::None => None,
+          format => format
+            .descriptor()
+            .map(|descriptor| descriptor.name().to_string()),
+        };
+
+        let rotation = stream
+          .side_data()
+          .find(|side_data| side_data.kind() == AvSideDataType::DisplayMatrix)
+          .and_then(|side_data| display_matrix_rotation(side_data.data()));
+
+        StreamKind::Video {
+          width,
+          height,
+          frame_rate,
+          pixel_format,
+          rotation,
+        }
+      },
+      AvMediaType::Audio => {
+        let (sample_rate, channels, sample_format) =
+          codec_parameters_audio(&parameters);
+
+        let sample_format = match sample_format {
+          AvSample::None => None,
+          format => Some(format.name().to_string()),
+        };
+
+        StreamKind::Audio {
+          sample_rate,
+          channels,
+          sample_format,
+        }
+      },
+      AvMediaType::Subtitle => StreamKind::Subtitle,
+      AvMediaType::Data => StreamKind::Data,
+      AvMediaType::Attachment => StreamKind::Attachment,
+      AvMediaType::Unknown => StreamKind::Unknown,
+    };
+
+    Ok(Self {
+      index: stream.index(),
+      codec_name: parameters.id().name().to_string(),
+      start_time: timestamp(stream.start_time(), time_base),
+      duration: timestamp(stream.duration(), time_base),
+      bit_rate: positive(codec_parameters_bit_rate(&parameters)),
+      tags: tags(stream.metadata()),
+      kind,
+    })
+  }
+
+}
+
+/// Media specific information about a stream.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamKind {
+  Video {
+    /// Width in pixels.
+    width: u32,
+    /// Height in pixels.
+    height: u32,
+    /// Frame rate as numerator and denominator, if known.
+    frame_rate: Option<(i32, i32)>,
+    /// Name of the pixel format, like "yuv420p".
+    pixel_format: Option<String>,
+    /// Counterclockwise rotation in degrees that must be applied to the
+    /// frames for correct presentation, if the stream has a display matrix.
+    rotation: Option<f64>,
+  },
+  Audio {
+    /// Sample rate in Hz.
+    sample_rate: u32,
+    /// Number of channels.
+    channels: u16,
+    /// Name of the sample format, like "fltp".
+    sample_format: Option<String>,
+  },
+  Subtitle,
+  Data,
+  Attachment,
+  Unknown,
+}
+
+/// Convert backend timestamp to `Time`, taking into account that the
+/// backend uses `AV_NOPTS_VALUE` for unknown timestamps.
+fn timestamp(value: i64, time_base: AvRational) -> Time {
+  Time::new(
+    if value != AV_NOPTS_VALUE { Some(value) } else { None },
+    time_base,
+  )
+}
+
+/// Backend uses zero or negative values for unknown bit rates.
+fn positive(value: i64) -> Option<usize> {
+  if value > 0 { Some(value as usize) } else { None }
+}
+
+/// Collect metadata dictionary into owned tags.
+fn tags(metadata: AvDictionaryRef) -> HashMap<String, String> {
+  metadata
+    .iter()
+    .map(|(key, value)| (key.to_string(), value.to_string()))
+    .collect()
+}
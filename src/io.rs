@@ -1,7 +1,11 @@
 extern crate ffmpeg_next as ffmpeg;
 
 use std::path::{PathBuf, Path};
+use std::io::{Read, Seek, Cursor};
 use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 use ffmpeg::{
   ffi::AV_TIME_BASE_Q,
@@ -9,12 +13,17 @@ use ffmpeg::{
   format::context::Output as AvOutput,
   codec::packet::Packet as AvPacket,
   media::Type as AvMediaType,
+  util::error::{EAGAIN, EINVAL},
   Error as AvError,
 };
 
 use super::StreamInfo;
+use super::MediaInfo;
 use super::Packet;
+use super::Time;
+use super::Timecode;
 use super::Error;
+use super::error::Operation;
 use super::options::Options;
 use super::ffi;
 
@@ -23,10 +32,59 @@ type Result<T> = std::result::Result<T, Error>;
 /// Re-export `url::Url` since it is an input type for callers of the API.
 pub use url::Url;
 
-/// Video reader that can read from files.
+/// Handle that can be used to cancel blocking operations on a `Reader`,
+/// possibly from another thread. Operations that are cancelled return
+/// `Error::Interrupted`.
+/// 
+/// Once cancelled, all subsequent operations on readers that use the token
+/// are interrupted until the token is reset.
+/// 
+/// Note: Operations that are interrupted because their deadline passed
+/// return `Error::TimedOut` instead.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+
+  /// Create a new token that has not been cancelled.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Cancel any ongoing and future operations that use this token.
+  pub fn cancel(&self) {
+    self.0.store(true, Ordering::SeqCst);
+  }
+
+  /// Reset the token so that operations can proceed again.
+  pub fn reset(&self) {
+    self.0.store(false, Ordering::SeqCst);
+  }
+
+  /// Whether or not the token has been cancelled.
+  pub fn is_cancelled(&self) -> bool {
+    self.0.load(Ordering::SeqCst)
+  }
+
+}
+
+/// Video reader that can read from files, URLs, memory or any type that
+/// implements `std::io::Read` and `std::io::Seek`.
 pub struct Reader {
+  /// Source the reader was opened on.
+  /// 
+  /// Note: When reading from memory or a custom reader, there is no source
+  /// and this holds an empty path as placeholder, which does not point to
+  /// anything. Prefer `locator`, which returns `None` in that case.
   pub source: Locator,
   pub input: AvInput,
+  locator: Option<Locator>,
+  cancellation_token: CancellationToken,
+  read_timeout: Option<Duration>,
+  // Note: Must be declared after `input` so that they are dropped after
+  // the input has been closed.
+  interrupt: Box<ffi::Interrupt>,
+  _custom_io: Option<ffi::CustomInputIo>,
 }
 
 impl Reader {
@@ -37,12 +95,7 @@ impl Reader {
   /// 
   /// * `source` - Source to read from.
   pub fn new(source: &Locator) -> Result<Self> {
-    let input = ffmpeg::format::input(&source.resolve())?;
-
-    Ok(Self {
-      source: source.clone(),
-      input,
-    })
+    Self::new_with_options(source, &Options::default())
   }
 
   /// Create a new video file reader with options for the backend.
@@ -66,16 +119,184 @@ impl Reader {
   ///   .unwrap();
   /// ```
   pub fn new_with_options(source: &Locator, options: &Options) -> Result<Self> {
-    let input = ffmpeg::format::input_with_dictionary(
-      &source.resolve(),
-      options.to_dict())?;
+    Self::new_with_cancellation(
+      source,
+      options,
+      &CancellationToken::new(),
+      None)
+  }
+
+  /// Create a new video file reader that can be cancelled through the
+  /// provided token, and that gives up opening the source after a timeout.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `source` - Source to read from.
+  /// * `options` - Options to pass on.
+  /// * `cancellation_token` - Token to cancel opening and reading with.
+  /// * `open_timeout` - Maximum time opening the source may take.
+  /// 
+  /// # Examples
+  /// 
+  /// ```
+  /// let token = CancellationToken::new();
+  /// let mut reader = Reader::new_with_cancellation(
+  ///     &Url::parse("rtsp://camera/stream").unwrap().into(),
+  ///     &Options::new_with_rtsp_transport_tcp(),
+  ///     &token,
+  ///     Some(Duration::from_secs(10)))
+  ///   .unwrap();
+  /// reader.set_read_timeout(Some(Duration::from_secs(5)));
+  /// 
+  /// // Another thread may now call `token.cancel()` to abort reading.
+  /// ```
+  pub fn new_with_cancellation(
+    source: &Locator,
+    options: &Options,
+    cancellation_token: &CancellationToken,
+    open_timeout: Option<Duration>,
+  ) -> Result<Self> {
+    let interrupt = Box::new(ffi::Interrupt::new(
+      cancellation_token.0.clone()));
+
+    interrupt.set_deadline(open_timeout.map(|timeout| Instant::now() + timeout));
+    let input = ffi::input_with_interrupt(
+        &source.resolve(),
+        options.to_dict(),
+        &interrupt)
+      .map_err(|err| {
+        interrupted_or(&interrupt, err)
+          .with_context(Operation::Open, Some(source), None)
+      });
+    interrupt.set_deadline(None);
 
     Ok(Self {
       source: source.clone(),
+      input: input?,
+      locator: Some(source.clone()),
+      cancellation_token: cancellation_token.clone(),
+      read_timeout: None,
+      interrupt,
+      _custom_io: None,
+    })
+  }
+
+  /// Create a new video reader that reads from a buffer in memory, such
+  /// as a `Vec<u8>` or `bytes::Bytes`.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `buf` - Buffer that holds the contents of a video file.
+  /// 
+  /// # Examples
+  /// 
+  /// ```
+  /// let bytes = std::fs::read("my_file.mp4").unwrap();
+  /// let mut reader = Reader::new_from_buf(bytes).unwrap();
+  /// ```
+  pub fn new_from_buf<B>(buf: B) -> Result<Self>
+  where
+    B: AsRef<[u8]> + Send + 'static,
+  {
+    Self::new_from_read(Cursor::new(buf))
+  }
+
+  /// Create a new video reader that reads from a buffer in memory with
+  /// options for the backend.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `buf` - Buffer that holds the contents of a video file.
+  /// * `options` - Options to pass on.
+  pub fn new_from_buf_with_options<B>(
+    buf: B,
+    options: &Options,
+  ) -> Result<Self>
+  where
+    B: AsRef<[u8]> + Send + 'static,
+  {
+    Self::new_from_read_with_options(Cursor::new(buf), options)
+  }
+
+  /// Create a new video reader that reads from any type that implements
+  /// `Read` and `Seek`.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `read` - Reader to read video file contents from.
+  /// 
+  /// # Examples
+  /// 
+  /// ```
+  /// let file = std::fs::File::open("my_file.mp4").unwrap();
+  /// let mut reader = Reader::new_from_read(file).unwrap();
+  /// ```
+  pub fn new_from_read<R>(read: R) -> Result<Self>
+  where
+    R: Read + Seek + Send + 'static,
+  {
+    Self::new_from_read_with_options(read, &Options::default())
+  }
+
+  /// Create a new video reader that reads from any type that implements
+  /// `Read` and `Seek` with options for the backend.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `read` - Reader to read video file contents from.
+  /// * `options` - Options to pass on.
+  pub fn new_from_read_with_options<R>(
+    read: R,
+    options: &Options,
+  ) -> Result<Self>
+  where
+    R: Read + Seek + Send + 'static,
+  {
+    let cancellation_token = CancellationToken::new();
+    let interrupt = Box::new(ffi::Interrupt::new(
+      cancellation_token.0.clone()));
+
+    let (input, custom_io) = ffi::input_custom(
+        Box::new(read),
+        options.to_dict(),
+        &interrupt)
+      .map_err(|err| {
+        Error::from(err).with_context(Operation::Open, None, None)
+      })?;
+
+    Ok(Self {
+      source: Locator::Path(PathBuf::new()),
       input,
+      locator: None,
+      cancellation_token,
+      read_timeout: None,
+      interrupt,
+      _custom_io: Some(custom_io),
     })
   }
 
+  /// Get the source the reader was opened on, or `None` if the reader reads
+  /// from memory or a custom reader.
+  pub fn locator(&self) -> Option<&Locator> {
+    self.locator.as_ref()
+  }
+
+  /// Get the token that can be used to cancel blocking operations on this
+  /// reader, possibly from another thread.
+  pub fn cancellation_token(&self) -> CancellationToken {
+    self.cancellation_token.clone()
+  }
+
+  /// Set the maximum time a single call to `read` or `read_any` may take.
+  /// When the timeout passes, the call returns `Error::TimedOut`.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `timeout` - Read timeout, or `None` to wait indefinitely.
+  pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+    self.read_timeout = timeout;
+  }
+
   /// Read a single packet from the source video file.
   /// 
   /// # Arguments
@@ -92,25 +313,69 @@ impl Reader {
   /// let mut packet = reader.read(stream).unwrap();
   /// ```
   pub fn read(&mut self, stream_index: usize) -> Result<Packet> {
-    let mut error_count = 0;
-    loop {
-      match self.input.packets().next() {
-        Some((stream, packet)) => {
-          if stream.index() == stream_index {
-            return Ok(Packet::new(
-              packet,
-              stream.time_base(),
-            ))
-          }
-        },
-        None => {
-          error_count += 1;
-          if error_count > 3 {
-            return Err(Error::ReadExhausted)
+    self
+      .with_read_deadline(|reader| {
+        loop {
+          let (index, packet) = reader.read_next()?;
+          if index == stream_index {
+            return Ok(packet)
           }
         }
+      })
+      .map_err(|err| {
+        err.with_context(Operation::Read, self.locator(), Some(stream_index))
+      })
+  }
+
+  /// Read a single packet from any stream in the source video file.
+  /// Unlike `read`, this does not discard packets from other streams,
+  /// which makes it suitable for transmuxing all streams at once.
+  /// 
+  /// # Returns
+  /// 
+  /// A tuple consisting of:
+  /// * Index of the stream the packet belongs to.
+  /// * The packet.
+  /// 
+  /// # Examples
+  /// 
+  /// ```
+  /// let mut reader = Reader(&PathBuf::from("my_video.mp4").into()).unwrap();
+  /// let (stream_index, packet) = reader.read_any().unwrap();
+  /// ```
+  pub fn read_any(&mut self) -> Result<(usize, Packet)> {
+    self
+      .with_read_deadline(Self::read_next)
+      .map_err(|err| {
+        err.with_context(Operation::Read, self.locator(), None)
+      })
+  }
+
+  /// Iterate over all packets from all streams in the source video file,
+  /// until the reader is exhausted. Every item is a tuple of the stream
+  /// index and the packet, like `read_any`.
+  /// 
+  /// Note: Iteration ends when the reader is exhausted. Any other error is
+  /// yielded as item, after which the caller may decide to stop or go on.
+  /// 
+  /// # Examples
+  /// 
+  /// ```
+  /// let mut reader = Reader(&PathBuf::from("my_video.mp4").into()).unwrap();
+  /// for item in reader.packets() {
+  ///   let (stream_index, packet) = item.unwrap();
+  ///   println!("stream {}: {:?}", stream_index, packet.pts());
+  /// }
+  /// ```
+  pub fn packets(
+    &mut self,
+  ) -> impl Iterator<Item = Result<(usize, Packet)>> + '_ {
+    std::iter::from_fn(move || {
+      match self.read_any() {
+        Err(err) if err.is_eof() => None,
+        result => Some(result),
       }
-    }
+    })
   }
 
   /// Retrieve stream information for a stream. Stream information can be
@@ -129,6 +394,42 @@ impl Reader {
     )
   }
 
+  /// Inspect the container and all of its streams. This produces a typed
+  /// description of format, duration, bit rate, codecs and metadata tags.
+  /// 
+  /// # Examples
+  /// 
+  /// ```
+  /// let reader = Reader::new(&PathBuf::from("my_video.mp4").into()).unwrap();
+  /// let media_info = reader.media_info().unwrap();
+  /// for stream in media_info.video_streams() {
+  ///   println!("{}: {:?}", stream.codec_name, stream.kind);
+  /// }
+  /// ```
+  pub fn media_info(&self) -> Result<MediaInfo> {
+    MediaInfo::from_reader(&self)
+  }
+
+  /// Get the start timecode of a stream from its `timecode` tag. If the
+  /// stream itself has no such tag, the container-level tag is used.
+  /// Returns `None` if neither is present.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `stream_index` - Index of stream to get timecode of.
+  pub fn timecode(&self, stream_index: usize) -> Result<Option<Timecode>> {
+    let stream = self.input
+      .stream(stream_index)
+      .ok_or(AvError::StreamNotFound)?;
+
+    stream
+      .metadata()
+      .get(Timecode::TAG)
+      .or_else(|| self.input.metadata().get(Timecode::TAG))
+      .map(|timecode| timecode.parse::<Timecode>())
+      .transpose()
+  }
+
   /// Seek in reader. This will change the reader head so that it points to
   /// a location within one second of the target timestamp or it will return
   /// an error.
@@ -149,7 +450,28 @@ impl Reader {
     self
       .input
       .seek(timestamp, range)
-      .map_err(Error::BackendError)
+      .map_err(|err| self.seek_error(err))
+  }
+
+  /// Seek in reader to the last key frame at or before the target timestamp.
+  /// Decoding from there on will eventually yield the frame at the target
+  /// timestamp. If the timestamp has no value, this seeks to the start.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `timestamp` - Target timestamp.
+  pub fn seek_to_key_frame(&mut self, timestamp: &Time) -> Result<()> {
+    match timestamp.aligned(AV_TIME_BASE_Q.into()).into_value() {
+      Some(timestamp) => {
+        self
+          .input
+          .seek(timestamp, ..timestamp)
+          .map_err(|err| self.seek_error(err))
+      },
+      None => {
+        self.seek_to_start()
+      },
+    }
   }
 
   /// Seek to start of reader. This function performs best effort seeking to
@@ -158,7 +480,77 @@ impl Reader {
     self
       .input
       .seek(i64::min_value(), ..)
-      .map_err(Error::BackendError)
+      .map_err(|err| self.seek_error(err))
+  }
+
+  /// Read the next packet from any stream.
+  fn read_next(&mut self) -> Result<(usize, Packet)> {
+    // Number of transient errors in a row after which reading gives up.
+    const MAX_SKIPPED_ERRORS: usize = 16;
+
+    let mut error_count = 0;
+    let mut skipped_error_count = 0;
+    loop {
+      let mut packet = AvPacket::empty();
+      match packet.read(&mut self.input) {
+        Ok(()) => {
+          let stream = self
+            .input
+            .stream(packet.stream())
+            .ok_or(AvError::StreamNotFound)?;
+          return Ok((
+            stream.index(),
+            Packet::new(
+              packet,
+              stream.time_base(),
+            ),
+          ))
+        },
+        Err(AvError::Eof) => {
+          error_count += 1;
+          if error_count > 3 {
+            return Err(Error::ReadExhausted)
+          }
+        },
+        // Transient errors are skipped, like the backend does when
+        // iterating over packets. Since a broken source may keep returning
+        // them, only a limited number is skipped.
+        Err(err) if is_transient(&err) &&
+                    !self.interrupt.is_triggered() &&
+                    skipped_error_count < MAX_SKIPPED_ERRORS => {
+          skipped_error_count += 1;
+        },
+        Err(err) => {
+          return Err(interrupted_or(&self.interrupt, err))
+        },
+      }
+    }
+  }
+
+  /// Turn backend error during seeking into error with context.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `err` - Backend error.
+  fn seek_error(&self, err: AvError) -> Error {
+    interrupted_or(&self.interrupt, err)
+      .with_context(Operation::Seek, self.locator(), None)
+  }
+
+  /// Run a read operation with the read timeout (if any) as deadline.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `read` - Read operation to run.
+  fn with_read_deadline<T>(
+    &mut self,
+    read: impl FnOnce(&mut Self) -> Result<T>,
+  ) -> Result<T> {
+    self.interrupt.set_deadline(
+      self.read_timeout.map(|timeout| Instant::now() + timeout));
+    let result = read(self);
+    self.interrupt.set_deadline(None);
+    result
   }
 
   /// Find the best video stream and return the index.
@@ -170,6 +562,47 @@ impl Reader {
       .index())
   }
 
+  /// Find the best audio stream and return the index.
+  pub fn best_audio_stream_index(&self) -> Result<usize> {
+    Ok(self.input
+      .streams()
+      .best(AvMediaType::Audio)
+      .ok_or(AvError::StreamNotFound)?
+      .index())
+  }
+
+}
+
+/// Turn an error into `Error::Interrupted` or `Error::TimedOut` if the
+/// interrupt was triggered, since that is the reason the backend operation
+/// failed.
+/// 
+/// # Arguments
+/// 
+/// * `interrupt` - Interrupt of the operation.
+/// * `err` - Backend error.
+fn interrupted_or(interrupt: &ffi::Interrupt, err: AvError) -> Error {
+  if interrupt.is_cancelled() {
+    Error::Interrupted
+  } else if interrupt.is_triggered() {
+    Error::TimedOut
+  } else {
+    Error::BackendError(err)
+  }
+}
+
+/// Whether or not a backend error while reading packets is transient, like
+/// a corrupt packet or a source that has no data available yet. Reading can
+/// continue after such errors.
+/// 
+/// # Arguments
+/// 
+/// * `err` - Backend error.
+fn is_transient(err: &AvError) -> bool {
+  matches!(
+    err,
+    AvError::InvalidData |
+    AvError::Other { errno: EAGAIN | EINVAL })
 }
 
 /// Any type that implements this can write video packets.
@@ -425,7 +858,7 @@ impl Write for PacketizedBufWriter {}
 /// Wrapper type for any valid video source. Currently, this could be
 /// a URI, file path or any other input the backend will accept. Later,
 /// we might add some scaffolding to have stricter typing.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Locator {
   Path(PathBuf),
   Url(Url)
@@ -480,6 +913,7 @@ pub(crate) mod private {
   use super::{
     AvOutput,
     AvPacket,
+    Locator,
     Writer,
     BufWriter,
     PacketizedBufWriter,
@@ -492,7 +926,7 @@ pub(crate) mod private {
   type Result<T> = std::result::Result<T, Error>;
 
   pub trait Write {
-    type Out;
+    type Out: Default;
 
     /// Write the container header.
     fn write_header(&mut self) -> Result<Self::Out>;
@@ -513,6 +947,14 @@ pub(crate) mod private {
 
     /// Write the container trailer.
     fn write_trailer(&mut self) -> Result<Self::Out>;
+
+    /// Append output of a write to output of earlier writes.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `out` - Output of earlier writes.
+    /// * `other` - Output to append.
+    fn extend_out(out: &mut Self::Out, other: Self::Out);
   }
 
   impl Write for Writer {
@@ -536,6 +978,8 @@ pub(crate) mod private {
       Ok(self.output.write_trailer()?)
     }
 
+    fn extend_out(_out: &mut (), _other: ()) {}
+
   }
 
   impl Write for BufWriter {
@@ -567,6 +1011,10 @@ pub(crate) mod private {
       Ok(self.end_write())
     }
 
+    fn extend_out(out: &mut Buf, other: Buf) {
+      out.extend(other);
+    }
+
   }
 
   impl Write for PacketizedBufWriter {
@@ -602,6 +1050,10 @@ pub(crate) mod private {
       Ok(self.take_buffers())
     }
 
+    fn extend_out(out: &mut Bufs, other: Bufs) {
+      out.extend(other);
+    }
+
   }
 
   pub trait Output {
@@ -612,6 +1064,11 @@ pub(crate) mod private {
     /// Obtain mutable reference to output context.
     fn output_mut(&mut self) -> &mut AvOutput;
 
+    /// Obtain destination the output writes to, if it has one.
+    fn dest(&self) -> Option<&Locator> {
+      None
+    }
+
   }
 
   impl Output for Writer {
@@ -624,6 +1081,10 @@ pub(crate) mod private {
       &mut self.output
     }
 
+    fn dest(&self) -> Option<&Locator> {
+      Some(&self.dest)
+    }
+
   }
 
   impl Output for BufWriter {
@@ -5,27 +5,45 @@ mod rtp;
 mod options;
 mod io;
 mod stream;
+mod info;
+mod live;
 mod frame;
 mod packet;
 mod time;
+mod timecode;
 mod extradata;
+mod sps;
+mod codec_string;
+mod bitstream;
 mod ffi;
 mod init;
 mod error;
 
 pub use io::{
   Reader,
+  CancellationToken,
   Write,
   Writer,
   Buf,
 };
 pub use decode::{
   Decoder,
+  AudioDecoder,
+  PacketDecoder,
   Resize,
 };
 pub use encode::{
   Encoder,
+  PacketEncoder,
   Settings as EncoderSettings,
+  RateControl,
+  AudioSettings as AudioEncoderSettings,
+};
+pub use live::{
+  LiveReader,
+  LiveDecoder,
+  LiveEvent,
+  LiveSettings,
 };
 pub use mux::{
   FileMuxer,
@@ -42,18 +60,47 @@ pub use io::{
   Url
 };
 pub use stream::StreamInfo;
-pub use frame::RawFrame;
+pub use info::{
+  MediaInfo,
+  StreamMediaInfo,
+  StreamKind,
+};
+pub use frame::{
+  RawFrame,
+  RawAudioFrame,
+};
 pub use time::{
   Time,
   Aligned,
 };
-pub use packet::Packet;
+pub use timecode::Timecode;
+pub use packet::{
+  Packet,
+  PacketSideDataType,
+};
 pub use extradata::{
   Sps,
-  Pps
+  Pps,
+  ParameterSetsHevc,
+};
+pub use bitstream::{
+  BitstreamConverter,
+  BitstreamFilter,
+  BitstreamFormat,
+};
+pub use sps::{
+  SequenceParameterSet,
+  VuiTiming,
+};
+pub use error::{
+  Error,
+  Context as ErrorContext,
+  Operation,
 };
-pub use error::Error;
 pub use init::init;
 
 #[cfg(feature = "ndarray")]
-pub use frame::Frame;
\ No newline at end of file
+pub use frame::{
+  Frame,
+  AudioFrame,
+};
\ No newline at end of file
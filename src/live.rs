@@ -0,0 +1,719 @@
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{
+  Error,
+  Locator,
+  Packet,
+  RawFrame,
+  StreamInfo,
+  Time,
+  Url,
+  decode::{PacketDecoder, Resize},
+  info::{MediaInfo, StreamKind},
+  io::{Reader, CancellationToken},
+  options::Options,
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Settings that control how a live source is supervised: how long
+/// operations may take before the connection is considered broken, and
+/// how long to wait in between reconnection attempts.
+#[derive(Clone, Debug)]
+pub struct LiveSettings {
+  open_timeout: Option<Duration>,
+  read_timeout: Option<Duration>,
+  initial_backoff: Duration,
+  max_backoff: Duration,
+  backoff_multiplier: u32,
+  max_attempts: Option<u32>,
+  discontinuity_threshold: Duration,
+}
+
+impl LiveSettings {
+
+  /// Default maximum time opening the source may take.
+  const OPEN_TIMEOUT: Duration = Duration::from_secs(10);
+
+  /// Default maximum time reading a single packet may take.
+  const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+  /// Default time to wait after the first failed connection attempt.
+  const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+  /// Default maximum time to wait in between reconnection attempts.
+  const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+  /// Default factor by which the backoff grows after each failed attempt.
+  const BACKOFF_MULTIPLIER: u32 = 2;
+
+  /// Default maximum jump in timestamps that is not considered to be a
+  /// discontinuity.
+  const DISCONTINUITY_THRESHOLD: Duration = Duration::from_secs(1);
+
+  /// Create settings with default timeouts and exponential backoff that
+  /// keeps trying to reconnect indefinitely.
+  pub fn new() -> Self {
+    Self {
+      open_timeout: Some(Self::OPEN_TIMEOUT),
+      read_timeout: Some(Self::READ_TIMEOUT),
+      initial_backoff: Self::INITIAL_BACKOFF,
+      max_backoff: Self::MAX_BACKOFF,
+      backoff_multiplier: Self::BACKOFF_MULTIPLIER,
+      max_attempts: None,
+      discontinuity_threshold: Self::DISCONTINUITY_THRESHOLD,
+    }
+  }
+
+  /// Set the maximum time opening the source may take.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `timeout` - Open timeout, or `None` to wait indefinitely.
+  pub fn with_open_timeout(mut self, timeout: Option<Duration>) -> Self {
+    self.open_timeout = timeout;
+    self
+  }
+
+  /// Set the maximum time reading a single packet may take. When reading
+  /// takes longer, the connection is considered to be broken.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `timeout` - Read timeout, or `None` to wait indefinitely.
+  pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+    self.read_timeout = timeout;
+    self
+  }
+
+  /// Set the backoff in between reconnection attempts. The first attempt
+  /// is made right away. If it fails, the second attempt is made after
+  /// `initial`, and every subsequent attempt waits `multiplier` times
+  /// longer than the previous one, up to `max`.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `initial` - Time to wait after the first failed attempt.
+  /// * `max` - Maximum time to wait in between attempts.
+  /// * `multiplier` - Factor by which the wait time grows.
+  pub fn with_backoff(
+    mut self,
+    initial: Duration,
+    max: Duration,
+    multiplier: u32,
+  ) -> Self {
+    self.initial_backoff = initial;
+    self.max_backoff = max;
+    self.backoff_multiplier = multiplier;
+    self
+  }
+
+  /// Set the maximum number of consecutive reconnection attempts. After
+  /// that, the last error is returned to the caller.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `max_attempts` - Maximum attempts, or `None` to try indefinitely.
+  pub fn with_max_attempts(mut self, max_attempts: Option<u32>) -> Self {
+    self.max_attempts = max_attempts;
+    self
+  }
+
+  /// Set the largest jump in timestamps between two consecutive packets of
+  /// the same stream that is not reported as a discontinuity. Timestamps
+  /// going backwards are always reported.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `threshold` - Discontinuity threshold.
+  pub fn with_discontinuity_threshold(mut self, threshold: Duration) -> Self {
+    self.discontinuity_threshold = threshold;
+    self
+  }
+
+  /// Time to wait after a failed connection attempt, or `None` if the
+  /// maximum number of attempts has been reached.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `attempt` - Number of the failed attempt, starting at one.
+  fn backoff(&self, attempt: u32) -> Option<Duration> {
+    if self.max_attempts.map(|max| attempt > max).unwrap_or(false) {
+      return None;
+    }
+
+    let multiplier = self.backoff_multiplier
+      .checked_pow(attempt - 1)
+      .unwrap_or(u32::MAX);
+
+    Some(
+      self.initial_backoff
+        .checked_mul(multiplier)
+        .unwrap_or(self.max_backoff)
+        .min(self.max_backoff))
+  }
+
+}
+
+impl Default for LiveSettings {
+
+  fn default() -> Self {
+    Self::new()
+  }
+
+}
+
+/// Event produced by a supervised live source.
+#[derive(Clone, Debug)]
+pub enum LiveEvent<T> {
+  /// Regular item, like a packet or frame.
+  Item(T),
+  /// The connection was lost and has been re-established. Stream
+  /// parameters have been validated to be the same as before.
+  Reconnected {
+    /// Number of attempts it took to reconnect.
+    attempts: u32,
+  },
+  /// Timestamps of a stream jumped backwards or further forward than the
+  /// discontinuity threshold. The item with the `current` timestamp is
+  /// returned next.
+  Discontinuity {
+    /// Index of stream in which the discontinuity occurred.
+    stream_index: usize,
+    /// Timestamp of the last item before the discontinuity.
+    previous: Time,
+    /// Timestamp of the first item after the discontinuity.
+    current: Time,
+  },
+  /// A packet could not be decoded, for example because it was corrupt.
+  /// The decoder has been reset and skips packets until the next keyframe.
+  /// Only produced by `LiveDecoder`.
+  DecodeFailed {
+    /// Index of stream in which decoding failed.
+    stream_index: usize,
+    /// Error produced by the decoder.
+    error: Error,
+  },
+}
+
+/// Reader for live sources, like RTSP cameras, that automatically
+/// reconnects when the connection is lost or stalls.
+/// 
+/// # Example
+/// 
+/// ```
+/// let mut reader = LiveReader::new(
+///     &Url::parse("rtsp://camera/stream").unwrap(),
+///     LiveSettings::new())
+///   .unwrap();
+/// 
+/// loop {
+///   match reader.read().unwrap() {
+///     LiveEvent::Item((stream_index, packet)) => { /* ... */ },
+///     LiveEvent::Reconnected { attempts } =>
+///       println!("reconnected after {} attempts", attempts),
+///     LiveEvent::Discontinuity { .. } =>
+///       println!("timestamps jumped"),
+///     _ => {},
+///   }
+/// }
+/// ```
+pub struct LiveReader {
+  source: Locator,
+  options: Options<'static>,
+  settings: LiveSettings,
+  cancellation_token: CancellationToken,
+  reader: Reader,
+  media_info: MediaInfo,
+  last_timestamps: HashMap<usize, Time>,
+  pending: Option<(usize, Packet)>,
+}
+
+impl LiveReader {
+
+  /// Connect to a live source.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `source` - URL of live source.
+  /// * `settings` - Supervision settings.
+  pub fn new(source: &Url, settings: LiveSettings) -> Result<Self> {
+    Self::new_with_options(source, Options::default(), settings)
+  }
+
+  /// Connect to a live source with options for the backend. The options
+  /// are used again for every reconnection attempt.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `source` - URL of live source.
+  /// * `options` - Options to pass on.
+  /// * `settings` - Supervision settings.
+  pub fn new_with_options(
+    source: &Url,
+    options: Options<'static>,
+    settings: LiveSettings,
+  ) -> Result<Self> {
+    let source = Locator::Url(source.clone());
+    let cancellation_token = CancellationToken::new();
+
+    let (reader, _) = connect_with_backoff(
+      &source,
+      &options,
+      &settings,
+      &cancellation_token)?;
+    let media_info = reader.media_info()?;
+
+    Ok(Self {
+      source,
+      options,
+      settings,
+      cancellation_token,
+      reader,
+      media_info,
+      last_timestamps: HashMap::new(),
+      pending: None,
+    })
+  }
+
+  /// Read the next event. This is either a packet from any stream together
+  /// with its stream index, or a notification that the source reconnected
+  /// or that a timestamp discontinuity occurred.
+  /// 
+  /// Errors are only returned when the reader was cancelled, the maximum
+  /// number of reconnection attempts was reached, or the stream parameters
+  /// of the source changed after reconnecting.
+  pub fn read(&mut self) -> Result<LiveEvent<(usize, Packet)>> {
+    if let Some(item) = self.pending.take() {
+      return Ok(LiveEvent::Item(item));
+    }
+
+    match self.reader.read_any() {
+      Ok((stream_index, packet)) => {
+        match self.check_discontinuity(stream_index, &packet) {
+          Some(discontinuity) => {
+            self.pending = Some((stream_index, packet));
+            Ok(discontinuity)
+          },
+          None => {
+            Ok(LiveEvent::Item((stream_index, packet)))
+          },
+        }
+      },
+      Err(err) if err.is_interrupted() => {
+        Err(err)
+      },
+      Err(_) => {
+        let attempts = self.reconnect()?;
+        Ok(LiveEvent::Reconnected { attempts })
+      },
+    }
+  }
+
+  /// Get the token that can be used to cancel reading and reconnecting,
+  /// possibly from another thread.
+  pub fn cancellation_token(&self) -> CancellationToken {
+    self.cancellation_token.clone()
+  }
+
+  /// Get media information of the source. This stays the same across
+  /// reconnects.
+  pub fn media_info(&self) -> &MediaInfo {
+    &self.media_info
+  }
+
+  /// Retrieve stream information for a stream of the current connection.
+  /// Note that this must be fetched again after reconnecting.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `stream_index` - Index of stream to produce information for.
+  pub fn stream_info(&self, stream_index: usize) -> Result<StreamInfo> {
+    self.reader.stream_info(stream_index)
+  }
+
+  /// Find the best video stream and return the index.
+  pub fn best_video_stream_index(&self) -> Result<usize> {
+    self.reader.best_video_stream_index()
+  }
+
+  /// Replace the current connection with a new one.
+  /// 
+  /// # Returns
+  /// 
+  /// Number of attempts it took to reconnect.
+  fn reconnect(&mut self) -> Result<u32> {
+    let (reader, attempts) = connect_with_backoff(
+      &self.source,
+      &self.options,
+      &self.settings,
+      &self.cancellation_token)?;
+
+    if !is_compatible(&self.media_info, &reader.media_info()?) {
+      return Err(Error::StreamParametersChanged);
+    }
+
+    // Dropping the old reader closes the old connection.
+    self.reader = reader;
+
+    Ok(attempts)
+  }
+
+  /// Check whether the packet timestamp is discontinuous with the
+  /// timestamp of the previous packet in the same stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `stream_index` - Index of stream of packet.
+  /// * `packet` - Packet to check.
+  fn check_discontinuity(
+    &mut self,
+    stream_index: usize,
+    packet: &Packet,
+  ) -> Option<LiveEvent<(usize, Packet)>> {
+    let current = if packet.dts().has_value() {
+      packet.dts()
+    } else {
+      packet.pts()
+    };
+
+    if !current.has_value() {
+      return None;
+    }
+
+    let previous = self
+      .last_timestamps
+      .insert(stream_index, current)?;
+
+    let delta_millis = current
+      .aligned_with(&previous)
+      .subtract()
+      .aligned((1, 1000).into())
+      .into_value()?;
+
+    let threshold_millis =
+      self.settings.discontinuity_threshold.as_millis() as i64;
+
+    if delta_millis < 0 || delta_millis > threshold_millis {
+      Some(LiveEvent::Discontinuity {
+        stream_index,
+        previous,
+        current,
+      })
+    } else {
+      None
+    }
+  }
+
+}
+
+/// Decoder for live sources, like RTSP cameras, that decodes the best video
+/// stream and automatically reconnects when the connection is lost or
+/// stalls. When a packet cannot be decoded, the decoder is reset and
+/// decoding resumes at the next keyframe.
+pub struct LiveDecoder {
+  reader: LiveReader,
+  reader_stream_index: usize,
+  resize: Option<Resize>,
+  decoder: PacketDecoder,
+  frames: VecDeque<RawFrame>,
+  awaiting_keyframe: bool,
+}
+
+impl LiveDecoder {
+
+  /// Connect to a live source and decode its best video stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `source` - URL of live source.
+  /// * `settings` - Supervision settings.
+  pub fn new(source: &Url, settings: LiveSettings) -> Result<Self> {
+    Self::new_with_options(source, Options::default(), settings)
+  }
+
+  /// Connect to a live source with options for the backend and decode its
+  /// best video stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `source` - URL of live source.
+  /// * `options` - Options to pass on.
+  /// * `settings` - Supervision settings.
+  pub fn new_with_options(
+    source: &Url,
+    options: Options<'static>,
+    settings: LiveSettings,
+  ) -> Result<Self> {
+    Self::from_reader(
+      LiveReader::new_with_options(source, options, settings)?,
+      None,
+    )
+  }
+
+  /// Connect to a live source with options for the backend and decode its
+  /// best video stream, resizing each frame.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `source` - URL of live source.
+  /// * `options` - Options to pass on.
+  /// * `settings` - Supervision settings.
+  /// * `resize` - How to resize frames.
+  pub fn new_with_options_and_resize(
+    source: &Url,
+    options: Options<'static>,
+    settings: LiveSettings,
+    resize: Resize,
+  ) -> Result<Self> {
+    Self::from_reader(
+      LiveReader::new_with_options(source, options, settings)?,
+      Some(resize),
+    )
+  }
+
+  /// Create a live decoder from a live reader.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `reader` - Live reader to decode from.
+  /// * `resize` - Optional resize strategy to apply to frames.
+  pub fn from_reader(
+    reader: LiveReader,
+    resize: Option<Resize>,
+  ) -> Result<Self> {
+    let reader_stream_index = reader.best_video_stream_index()?;
+    let decoder = PacketDecoder::new_with_resize(
+      reader.stream_info(reader_stream_index)?,
+      resize)?;
+
+    Ok(Self {
+      reader,
+      reader_stream_index,
+      resize,
+      decoder,
+      frames: VecDeque::new(),
+      awaiting_keyframe: true,
+    })
+  }
+
+  /// Decode the next event. This is either a decoded frame, or a
+  /// notification that the source reconnected, that a timestamp
+  /// discontinuity occurred in the video stream or that a packet could not
+  /// be decoded.
+  /// 
+  /// Errors are only returned when the reader was cancelled, the maximum
+  /// number of reconnection attempts was reached, or the stream parameters
+  /// of the source changed after reconnecting.
+  pub fn decode_raw(&mut self) -> Result<LiveEvent<RawFrame>> {
+    loop {
+      if let Some(frame) = self.frames.pop_front() {
+        return Ok(LiveEvent::Item(frame));
+      }
+
+      match self.reader.read()? {
+        LiveEvent::Item((stream_index, packet)) => {
+          if stream_index != self.reader_stream_index {
+            continue;
+          }
+
+          if self.awaiting_keyframe {
+            if !packet.is_key() {
+              continue;
+            }
+            self.awaiting_keyframe = false;
+          }
+
+          match self.decoder.decode_raw(packet) {
+            Ok(frames) => {
+              self.frames.extend(frames);
+            },
+            Err(error) => {
+              // Decoder state cannot be trusted after an error, so start
+              // over with a fresh decoder at the next keyframe.
+              self.reset_decoder()?;
+              return Ok(LiveEvent::DecodeFailed { stream_index, error });
+            },
+          }
+        },
+        LiveEvent::Reconnected { attempts } => {
+          // The new connection needs a fresh decoder since decoder state
+          // does not carry over and stream indices may differ.
+          self.reset_decoder()?;
+          return Ok(LiveEvent::Reconnected { attempts });
+        },
+        LiveEvent::Discontinuity { stream_index, previous, current } => {
+          if stream_index == self.reader_stream_index {
+            return Ok(LiveEvent::Discontinuity {
+              stream_index,
+              previous,
+              current,
+            });
+          }
+        },
+        LiveEvent::DecodeFailed { stream_index, error } => {
+          return Ok(LiveEvent::DecodeFailed { stream_index, error });
+        },
+      }
+    }
+  }
+
+  /// Get the token that can be used to cancel decoding and reconnecting,
+  /// possibly from another thread.
+  pub fn cancellation_token(&self) -> CancellationToken {
+    self.reader.cancellation_token()
+  }
+
+  /// Get the decoder output frame size.
+  pub fn size(&self) -> (u32, u32) {
+    self.decoder.size()
+  }
+
+  /// Replace the decoder with a fresh one for the video stream of the
+  /// current connection, which starts decoding at the next keyframe.
+  fn reset_decoder(&mut self) -> Result<()> {
+    self.reader_stream_index = self.reader.best_video_stream_index()?;
+    self.decoder = PacketDecoder::new_with_resize(
+      self.reader.stream_info(self.reader_stream_index)?,
+      self.resize)?;
+    self.awaiting_keyframe = true;
+    Ok(())
+  }
+
+}
+
+/// Open a reader on the source, applying the timeouts from the settings.
+/// 
+/// # Arguments
+/// 
+/// * `source` - Source to read from.
+/// * `options` - Options to pass on.
+/// * `settings` - Supervision settings.
+/// * `cancellation_token` - Token to cancel with.
+fn connect(
+  source: &Locator,
+  options: &Options,
+  settings: &LiveSettings,
+  cancellation_token: &CancellationToken,
+) -> Result<Reader> {
+  let mut reader = Reader::new_with_cancellation(
+    source,
+    options,
+    cancellation_token,
+    settings.open_timeout)?;
+  reader.set_read_timeout(settings.read_timeout);
+  Ok(reader)
+}
+
+/// Keep trying to open a reader on the source until it succeeds, the
+/// maximum number of attempts is reached or the token is cancelled.
+/// 
+/// # Returns
+/// 
+/// A tuple consisting of:
+/// * The reader.
+/// * Number of attempts it took.
+fn connect_with_backoff(
+  source: &Locator,
+  options: &Options,
+  settings: &LiveSettings,
+  cancellation_token: &CancellationToken,
+) -> Result<(Reader, u32)> {
+  let mut attempt = 1;
+  loop {
+    match connect(source, options, settings, cancellation_token) {
+      Ok(reader) => {
+        return Ok((reader, attempt))
+      },
+      Err(err) if err.is_interrupted() => {
+        return Err(err)
+      },
+      Err(err) => {
+        match settings.backoff(attempt) {
+          Some(backoff) => sleep(backoff, cancellation_token)?,
+          None => return Err(err),
+        }
+        attempt += 1;
+      },
+    }
+  }
+}
+
+/// Sleep for the given duration, waking up early with `Error::Interrupted`
+/// if the token is cancelled.
+/// 
+/// # Arguments
+/// 
+/// * `duration` - Time to sleep.
+/// * `cancellation_token` - Token to cancel with.
+fn sleep(
+  duration: Duration,
+  cancellation_token: &CancellationToken,
+) -> Result<()> {
+  // Interval at which to check for cancellation.
+  const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+  let deadline = Instant::now() + duration;
+  loop {
+    if cancellation_token.is_cancelled() {
+      return Err(Error::Interrupted);
+    }
+
+    let now = Instant::now();
+    if now >= deadline {
+      return Ok(());
+    }
+
+    thread::sleep((deadline - now).min(POLL_INTERVAL));
+  }
+}
+
+/// Whether or not the media described by `new` can be read in place of
+/// the media described by `old` without the caller noticing, i.e. it has
+/// the same streams with the same codecs and dimensions.
+/// 
+/// # Arguments
+/// 
+/// * `old` - Media information before reconnecting.
+/// * `new` - Media information after reconnecting.
+fn is_compatible(old: &MediaInfo, new: &MediaInfo) -> bool {
+  old.streams.len() == new.streams.len() &&
+  old.streams
+    .iter()
+    .zip(new.streams.iter())
+    .all(|(old, new)| {
+      old.codec_name == new.codec_name &&
+      match (&old.kind, &new.kind) {
+        (
+          StreamKind::Video { width, height, pixel_format, .. },
+          StreamKind::Video {
+            width: new_width,
+            height: new_height,
+            pixel_format: new_pixel_format,
+            ..
+          },
+        ) => {
+          width == new_width &&
+          height == new_height &&
+          pixel_format == new_pixel_format
+        },
+        (
+          StreamKind::Audio { sample_rate, channels, .. },
+          StreamKind::Audio {
+            sample_rate: new_sample_rate,
+            channels: new_channels,
+            ..
+          },
+        ) => {
+          sample_rate == new_sample_rate &&
+          channels == new_channels
+        },
+        (old_kind, new_kind) => {
+          mem::discriminant(old_kind) == mem::discriminant(new_kind)
+        },
+      }
+    })
+}
@@ -4,17 +4,22 @@ use std::collections::HashMap;
 
 use ffmpeg::{
   codec::Id as AvCodecId,
+  codec::packet::Packet as AvPacket,
   Rational as AvRational,
   Error as AvError,
 };
 
 use super::{
   Error,
+  error::Operation,
   Locator,
   Sps,
   Pps,
+  ParameterSetsHevc,
   Packet,
   StreamInfo,
+  Timecode,
+  BitstreamFilter,
 };
 use super::io::{
   Reader,
@@ -23,8 +28,12 @@ use super::io::{
   BufWriter,
   PacketizedBufWriter,
 };
-use super::extradata::extract_parameter_sets_h264;
-use super::ffi::extradata;
+use super::extradata::{
+  extract_parameter_sets_h264,
+  extract_parameter_sets_hevc,
+};
+use super::codec_string::codec_string;
+use super::ffi::{extradata, set_output_metadata};
 use super::options::Options;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -61,17 +70,18 @@ impl Muxer<Writer> {
   /// Mux to an MKV file.
   /// 
   /// ```
-  /// let reader = Reader::new(
+  /// let mut reader = Reader::new(
   ///     &PathBuf::from("from_file.mp4").into())
   ///   .unwrap();
   ///   
-  /// let muxer = Muxer::new_to_file(
+  /// let mut muxer = Muxer::new_to_file(
   ///     &PathBuf::from("to_file.mkv").into())
   ///   .unwrap()
   ///   .with_streams(&reader)
   ///   .unwrap();
   /// 
-  /// while let Ok(packet) = reader.read() {
+  /// for item in reader.packets() {
+  ///   let (_, packet) = item.unwrap();
   ///   muxer.mux(packet).unwrap();
   /// }
   /// 
@@ -140,15 +150,16 @@ impl Muxer<BufWriter> {
   /// Mux from file to mp4 and print length of first 100 buffer segments.
   /// 
   /// ```
-  /// let reader = Reader::new(&PathBuf::from("my_file.mp4").into())
+  /// let mut reader = Reader::new(&PathBuf::from("my_file.mp4").into())
   ///   .unwrap();
   /// let mut muxer = Muxer::new_to_buf("mp4")
   ///   .unwrap()
   ///   .with_streams(&reader)
   ///   .unwrap();
   ///
-  /// for _ in 0..100 {
-  ///   println!("len: {}", muxer.mux().unwrap().len());
+  /// for item in reader.packets().take(100) {
+  ///   let (_, packet) = item.unwrap();
+  ///   println!("len: {}", muxer.mux(packet).unwrap().len());
   /// }
   /// 
   /// muxer.finish()?;
@@ -216,8 +227,50 @@ impl<W: Write> Muxer<W> {
   /// * `stream_info` - Stream information. Usually this information is
   ///   retrieved by calling `reader.stream_info(index)`.
   pub fn with_stream(
+    self,
+    stream_info: StreamInfo,
+  ) -> Result<Self> {
+    self.add_stream(stream_info, None)
+  }
+
+  /// Add an output stream to the muxer that receives the packets of an
+  /// input stream after passing them through a bitstream filter. The
+  /// output stream is set up with the codec parameters produced by the
+  /// filter.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `filter` - Bitstream filter, created from the stream information
+  ///   of the input stream.
+  /// 
+  /// # Examples
+  /// 
+  /// ```
+  /// let stream_info = reader.stream_info(stream_index).unwrap();
+  /// let muxer = Muxer::new_to_file(&PathBuf::from("my_video.ts").into())
+  ///   .unwrap()
+  ///   .with_filtered_stream(
+  ///     BitstreamFilter::new(&stream_info, "h264_mp4toannexb").unwrap())
+  ///   .unwrap();
+  /// ```
+  pub fn with_filtered_stream(
+    self,
+    filter: BitstreamFilter,
+  ) -> Result<Self> {
+    let stream_info = filter.stream_info();
+    self.add_stream(stream_info, Some(filter))
+  }
+
+  /// Add an output stream, optionally preceded by a bitstream filter.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `stream_info` - Stream information of the (filtered) stream.
+  /// * `filter` - Bitstream filter to pass packets through, if any.
+  fn add_stream(
     mut self,
     stream_info: StreamInfo,
+    filter: Option<BitstreamFilter>,
   ) -> Result<Self> {
     let (index, codec_parameters, reader_stream_time_base) =
       stream_info.into_parts();
@@ -231,6 +284,7 @@ impl<W: Write> Muxer<W> {
     let stream_description = StreamDescription {
       index: writer_stream.index(),
       source_time_base: reader_stream_time_base,
+      filter,
     };
 
     self
@@ -240,6 +294,28 @@ impl<W: Write> Muxer<W> {
     Ok(self)
   }
 
+  /// Write a start timecode into the output. Containers that support
+  /// timecodes (like MOV, MP4 and MXF) use it for the video stream. When
+  /// transmuxing, the timecode of the source can be retrieved with
+  /// `reader.timecode(index)`.
+  /// 
+  /// Note: This must be called before muxing the first packet.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `timecode` - Timecode of the first frame.
+  pub fn with_timecode(
+    mut self,
+    timecode: &Timecode,
+  ) -> Result<Self> {
+    set_output_metadata(
+      self.writer.output_mut(),
+      Timecode::TAG,
+      &timecode.to_string())?;
+
+    Ok(self)
+  }
+
   /// Add output streams from reader to muxer. This will add all streams
   /// in the reader and duplicate them in the muxer. After calling this,
   /// it is safe to mux all packets from the provided reader.
@@ -265,35 +341,16 @@ impl<W: Write> Muxer<W> {
     packet: Packet,
   ) -> Result<W::Out> {
     if self.have_written_header {
-      let mut packet = packet.into_inner();
-      let stream_description = self
-        .mapping
-        .get(&packet.stream())
-        .ok_or_else(|| AvError::StreamNotFound)?;
-
-      let destination_stream = self
-        .writer
-        .output()
-        .stream(stream_description.index)
-        .ok_or_else(|| AvError::StreamNotFound)?;
-
-      packet.set_stream(destination_stream.index());
-      packet.set_position(-1);
-      packet.rescale_ts(
-        stream_description.source_time_base,
-        destination_stream.time_base(),
-      );
-
-      Ok({
-        if self.interleaved {
-          self.writer.write_interleaved(&mut packet)?
-        } else {
-          self.writer.write(&mut packet)?
-        }
-      })
+      let stream_index = packet.stream_index();
+      self
+        .filter_and_write(packet)
+        .map_err(|err| self.mux_error(err, Some(stream_index)))
     } else {
       self.have_written_header = true;
-      self.writer.write_header()
+      self
+        .writer
+        .write_header()
+        .map_err(|err| self.mux_error(err, None))
     }
   }
 
@@ -327,10 +384,163 @@ impl<W: Write> Muxer<W> {
       .collect::<Vec<_>>()
   }
 
+  /// Get parameter sets corresponding to each internal stream. The
+  /// parameter sets contain one or more VPSs (Video Parameter Sets),
+  /// SPSs (Sequence Parameter Sets) and PPSs (Picture Parameter Sets).
+  /// 
+  /// Note that this function only supports extracting parameter
+  /// sets for streams with the H.265/HEVC codec and will return
+  /// `Error::UnsupportedCodecParameterSets` for streams with another
+  /// type of codec.
+  pub fn parameter_sets_hevc<'param>(
+    &'param self,
+  ) -> Vec<Result<ParameterSetsHevc<'param>>> {
+    self
+      .writer
+      .output()
+      .streams()
+      .map(|stream| {
+        if stream.codec().unwrap().id() == AvCodecId::HEVC {
+          extract_parameter_sets_hevc(
+            extradata(
+              &self.writer.output(),
+              stream.index()
+            )?
+          )
+        } else {
+          Err(Error::UnsupporedCodecParameterSets)
+        }
+      })
+      .collect::<Vec<_>>()
+  }
+
+  /// Get the codec string as defined in RFC 6381 corresponding to each
+  /// internal stream, like `avc1.64001f` or `mp4a.40.2`. These can be
+  /// joined to form the `codecs` parameter of the MIME type of the output,
+  /// as required by Media Source Extensions.
+  /// 
+  /// Note that this function only supports H.264, H.265/HEVC, VP9, AV1,
+  /// AAC and Opus streams and will return `Error::UnsupportedCodecString`
+  /// for streams with another type of codec.
+  pub fn codec_strings(&self) -> Vec<Result<String>> {
+    self
+      .writer
+      .output()
+      .streams()
+      .map(|stream| codec_string(&stream.parameters()))
+      .collect::<Vec<_>>()
+  }
+
   /// Signal to the muxer that writing has finished. This will cause a
   /// trailer to be written if the container format has one.
   pub fn finish(&mut self) -> Result<W::Out> {
-    self.writer.write_trailer()
+    let mut out = self
+      .flush_filters()
+      .map_err(|err| self.mux_error(err, None))?;
+    let trailer_out = self
+      .writer
+      .write_trailer()
+      .map_err(|err| self.mux_error(err, None))?;
+    W::extend_out(&mut out, trailer_out);
+
+    Ok(out)
+  }
+
+  /// Pass a packet through the bitstream filter of its stream, if it has
+  /// one, and write the resulting packets.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `packet` - Packet to write.
+  fn filter_and_write(&mut self, packet: Packet) -> Result<W::Out> {
+    let filter = self
+      .mapping
+      .get_mut(&packet.stream_index())
+      .and_then(|stream_description| stream_description.filter.as_mut());
+
+    match filter {
+      Some(filter) => {
+        let packets = filter.filter(packet)?;
+        self.write_all(packets)
+      },
+      None => self.write(&mut packet.into_inner()),
+    }
+  }
+
+  /// Flush the bitstream filters of all streams and write the packets they
+  /// were still holding on to.
+  fn flush_filters(&mut self) -> Result<W::Out> {
+    if !self.have_written_header {
+      return Ok(W::Out::default());
+    }
+
+    let mut packets = Vec::new();
+    for stream_description in self.mapping.values_mut() {
+      if let Some(filter) = stream_description.filter.as_mut() {
+        packets.extend(filter.flush()?);
+      }
+    }
+
+    self.write_all(packets)
+  }
+
+  /// Write multiple packets, combining the output.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `packets` - Packets to write.
+  fn write_all(&mut self, packets: Vec<Packet>) -> Result<W::Out> {
+    let mut out = W::Out::default();
+    for packet in packets {
+      let packet_out = self.write(&mut packet.into_inner())?;
+      W::extend_out(&mut out, packet_out);
+    }
+
+    Ok(out)
+  }
+
+  /// Write a packet to the output stream that corresponds to its source
+  /// stream.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `packet` - Packet to write.
+  fn write(&mut self, packet: &mut AvPacket) -> Result<W::Out> {
+    let stream_description = self
+      .mapping
+      .get(&packet.stream())
+      .ok_or_else(|| AvError::StreamNotFound)?;
+
+    let destination_stream = self
+      .writer
+      .output()
+      .stream(stream_description.index)
+      .ok_or_else(|| AvError::StreamNotFound)?;
+
+    packet.set_stream(destination_stream.index());
+    packet.set_position(-1);
+    packet.rescale_ts(
+      stream_description.source_time_base,
+      destination_stream.time_base(),
+    );
+
+    Ok({
+      if self.interleaved {
+        self.writer.write_interleaved(packet)?
+      } else {
+        self.writer.write(packet)?
+      }
+    })
+  }
+
+  /// Attach context of the muxer to an error.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `err` - Error to attach context to.
+  /// * `stream_index` - Index of source stream, if applicable.
+  fn mux_error(&self, err: Error, stream_index: Option<usize>) -> Error {
+    err.with_context(Operation::Mux, self.writer.dest(), stream_index)
   }
 
 }
@@ -340,4 +550,5 @@ impl<W: Write> Muxer<W> {
 struct StreamDescription {
   index: usize,
   source_time_base: AvRational,
+  filter: Option<BitstreamFilter>,
 }
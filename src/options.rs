@@ -5,6 +5,7 @@ use std::collections::HashMap;
 use ffmpeg::Dictionary as AvDictionary;
 
 /// A wrapper type for ffmpeg options.
+#[derive(Clone)]
 pub struct Options<'a>(AvDictionary<'a>);
 
 impl Options<'_> {
@@ -72,6 +73,126 @@ impl Options<'_> {
     Self(opts)
   }
 
+  /// Default options for a H265/HEVC encoder.
+  pub fn new_hevc() -> Self {
+    let mut opts = AvDictionary::new();
+    // Set H265 encoder to the medium preset.
+    opts.set("preset", "medium");
+
+    Self(opts)
+  }
+
+  /// Options for a H265/HEVC encoder that are tuned for low-latency
+  /// encoding such as for real-time streaming.
+  pub fn new_hevc_realtime() -> Self {
+    let mut opts = AvDictionary::new();
+    // Set H265 encoder to the medium preset.
+    opts.set("preset", "medium");
+    // Tune for low latency
+    opts.set("tune", "zerolatency");
+
+    Self(opts)
+  }
+
+  /// Default options for a VP9 encoder.
+  pub fn new_vp9() -> Self {
+    let mut opts = AvDictionary::new();
+    // Good quality at reasonable speed.
+    opts.set("deadline", "good");
+    opts.set("cpu-used", "2");
+    // Allows for multithreaded encoding of a single frame.
+    opts.set("row-mt", "1");
+
+    Self(opts)
+  }
+
+  /// Options for a VP9 encoder that are tuned for low-latency encoding
+  /// such as for real-time streaming.
+  pub fn new_vp9_realtime() -> Self {
+    let mut opts = AvDictionary::new();
+    opts.set("deadline", "realtime");
+    opts.set("cpu-used", "8");
+    opts.set("row-mt", "1");
+    // Do not look ahead, every frame is output immediately.
+    opts.set("lag-in-frames", "0");
+
+    Self(opts)
+  }
+
+  /// Default options for an AV1 encoder.
+  /// 
+  /// Note: There are multiple AV1 encoders that each have their own speed
+  /// options. Options that do not apply to the encoder that ends up being
+  /// used are ignored.
+  pub fn new_av1() -> Self {
+    let mut opts = AvDictionary::new();
+    // libaom-av1
+    opts.set("usage", "good");
+    opts.set("cpu-used", "4");
+    opts.set("row-mt", "1");
+    // libsvtav1
+    opts.set("preset", "8");
+    // librav1e
+    opts.set("speed", "6");
+
+    Self(opts)
+  }
+
+  /// Options for an AV1 encoder that are tuned for low-latency encoding
+  /// such as for real-time streaming.
+  /// 
+  /// Note: There are multiple AV1 encoders that each have their own speed
+  /// options. Options that do not apply to the encoder that ends up being
+  /// used are ignored.
+  pub fn new_av1_realtime() -> Self {
+    let mut opts = AvDictionary::new();
+    // libaom-av1
+    opts.set("usage", "realtime");
+    opts.set("cpu-used", "8");
+    opts.set("row-mt", "1");
+    opts.set("lag-in-frames", "0");
+    // libsvtav1
+    opts.set("preset", "12");
+    // librav1e
+    opts.set("speed", "10");
+
+    Self(opts)
+  }
+
+  /// Default options for an FFV1 encoder. This uses FFV1 version 3 with
+  /// slice checksums, which is suitable for archival purposes.
+  pub fn new_ffv1() -> Self {
+    let mut opts = AvDictionary::new();
+    opts.set("level", "3");
+    opts.set("slicecrc", "1");
+    opts.set("slices", "4");
+
+    Self(opts)
+  }
+
+  /// Default options for an MJPEG encoder. This sets a fixed, high quality
+  /// scale of 3. The encoder passes this quality scale on to every frame,
+  /// like the `-q:v 3` command line option does.
+  pub fn new_mjpeg() -> Self {
+    let mut opts = AvDictionary::new();
+    opts.set("flags", "+qscale");
+    // Quality scale 3 multiplied by `FF_QP2LAMBDA`.
+    opts.set("global_quality", "354");
+
+    Self(opts)
+  }
+
+  /// Default options for a ProRes encoder. This selects the ProRes 422 HQ
+  /// profile.
+  pub fn new_prores() -> Self {
+    let mut opts = AvDictionary::new();
+    opts.set("profile", "hq");
+    // Some players only accept ProRes files from this vendor.
+    opts.set("vendor", "apl0");
+
+    Self(opts)
+  }
+
   /// Create custom options from a `HashMap`.
   /// 
   /// # Arguments
@@ -86,6 +207,16 @@ impl Options<'_> {
     Self(opts)
   }
 
+  /// Set an option, overwriting any previous value.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `key` - Option name.
+  /// * `value` - Option value.
+  pub(crate) fn set(&mut self, key: &str, value: &str) {
+    self.0.set(key, value);
+  }
+
   /// Convert back to ffmpeg native dictionary, which can be used
   /// with `ffmpeg_next` functions.
   pub(super) fn to_dict(&self) -> AvDictionary {
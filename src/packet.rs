@@ -2,8 +2,16 @@ extern crate ffmpeg_next as ffmpeg;
 
 use ffmpeg::Rational as AvRational;
 use ffmpeg::codec::packet::Packet as AvPacket;
+use ffmpeg::codec::packet::Flags as AvPacketFlags;
 
 use crate::time::Time;
+use crate::stream::StreamInfo;
+use crate::ffi::{packet_copy_props, packet_side_data};
+use crate::error::Error;
+
+/// Re-export the backend's packet side data type, since it is an output
+/// type of `Packet::side_data`.
+pub use ffmpeg::codec::packet::side_data::Type as PacketSideDataType;
 
 /// Represents a stream packet.
 #[derive(Clone)]
@@ -14,6 +22,44 @@ pub struct Packet {
 
 impl Packet {
 
+  /// Create a packet from raw payload bytes, for example to inject packets
+  /// received from elsewhere into a `Muxer`. The payload must be in the
+  /// format the stream expects, as described by its codec parameters.
+  /// 
+  /// The packet is not marked as keyframe; use `set_key` to do so.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `data` - Payload bytes.
+  /// * `timestamp` - Presentation and decoding timestamp of the packet.
+  /// * `stream_info` - Information of stream the packet belongs to. The
+  ///   packet gets the stream index and time base of this stream.
+  /// 
+  /// # Examples
+  /// 
+  /// ```
+  /// let stream_info = reader.stream_info(stream_index).unwrap();
+  /// let mut packet = Packet::from_bytes(
+  ///   &payload,
+  ///   &Time::from_millis(40),
+  ///   &stream_info);
+  /// packet.set_key(true);
+  /// muxer.mux(packet).unwrap();
+  /// ```
+  pub fn from_bytes(
+    data: &[u8],
+    timestamp: &Time,
+    stream_info: &StreamInfo,
+  ) -> Self {
+    let mut packet = Self::new(
+      AvPacket::copy(data),
+      stream_info.time_base());
+    packet.inner.set_stream(stream_info.index);
+    packet.set_pts(timestamp);
+    packet.set_dts(timestamp);
+    packet
+  }
+
   /// Get packet PTS (presentation timestamp).
   pub fn pts(&self) -> Time {
     Time::new(self.inner.pts(), self.time_base)
@@ -29,6 +75,49 @@ impl Packet {
     Time::new(Some(self.inner.duration()), self.time_base)
   }
 
+  /// Get the packet payload. Returns an empty slice for empty packets.
+  pub fn data(&self) -> &[u8] {
+    self.inner.data().unwrap_or(&[])
+  }
+
+  /// Get the size of the packet payload in bytes.
+  pub fn size(&self) -> usize {
+    self.inner.size()
+  }
+
+  /// Index of the stream the packet belongs to.
+  pub fn stream_index(&self) -> usize {
+    self.inner.stream()
+  }
+
+  /// Whether or not the packet contains a keyframe.
+  pub fn is_key(&self) -> bool {
+    self.inner.is_key()
+  }
+
+  /// Whether or not the packet is marked as corrupt by the demuxer.
+  pub fn is_corrupt(&self) -> bool {
+    self.inner.is_corrupt()
+  }
+
+  /// Iterate over the side data of the packet, as pairs of type and raw
+  /// bytes. Side data carries information that is not part of the payload
+  /// itself, like new extradata after a change of codec parameters.
+  pub fn side_data(
+    &self,
+  ) -> impl Iterator<Item = (PacketSideDataType, &[u8])> + '_ {
+    packet_side_data(&self.inner).into_iter()
+  }
+
+  /// Get new extradata carried by the packet, if the codec parameters
+  /// changed mid-stream.
+  pub fn new_extradata(&self) -> Option<&[u8]> {
+    self
+      .side_data()
+      .find(|(kind, _)| *kind == PacketSideDataType::NewExtraData)
+      .map(|(_, data)| data)
+  }
+
   /// Set packet PTS (presentation timestamp).
   pub fn set_pts(&mut self, timestamp: &Time) {
     self.inner.set_pts(timestamp.aligned(self.time_base).into_value());
@@ -46,6 +135,17 @@ impl Packet {
     }
   }
 
+  /// Mark the packet as keyframe or not.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `is_key` - Whether or not the packet contains a keyframe.
+  pub fn set_key(&mut self, is_key: bool) {
+    let mut flags = self.inner.flags();
+    flags.set(AvPacketFlags::KEY, is_key);
+    self.inner.set_flags(flags);
+  }
+
   /// Create a new packet.
   /// 
   /// # Arguments
@@ -62,6 +162,18 @@ impl Packet {
     }
   }
 
+  /// Create a new packet with the same properties (timestamps, flags,
+  /// stream index and side data) as this one, but a different payload.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `data` - Payload of new packet.
+  pub(crate) fn with_data(&self, data: &[u8]) -> Result<Self, Error> {
+    let mut inner = AvPacket::copy(data);
+    packet_copy_props(&mut inner, &self.inner)?;
+    Ok(Self::new(inner, self.time_base))
+  }
+
   /// Downcast to native inner type.
   pub(crate) fn into_inner(self) -> AvPacket {
     self.inner
@@ -2,10 +2,12 @@ use super::{
   Reader,
   PacketizedBufMuxer,
   StreamInfo,
+  BitstreamFilter,
   Packet,
   Error,
   Sps,
   Pps,
+  ParameterSetsHevc,
   io::Buf,
   ffi::{
     sdp,
@@ -46,6 +48,21 @@ impl RtpMuxer {
       .map(RtpMuxer)
   }
 
+  /// Add an output stream to the muxer that receives the packets of an
+  /// input stream after passing them through a bitstream filter.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `filter` - Bitstream filter, created from the stream information
+  ///   of the input stream.
+  pub fn with_filtered_stream(
+    self,
+    filter: BitstreamFilter,
+  ) -> Result<Self> {
+    self.0.with_filtered_stream(filter)
+      .map(RtpMuxer)
+  }
+
   /// Add output streams from reader to muxer. This will add all streams
   /// in the reader and duplicate them in the muxer. After calling this,
   /// it is safe to mux all packets from the provided reader.
@@ -87,6 +104,20 @@ impl RtpMuxer {
     self.0.parameter_sets_h264()
   }
 
+  /// Get parameter sets corresponding to each internal stream. The
+  /// parameter sets contain one or more VPSs (Video Parameter Sets),
+  /// SPSs (Sequence Parameter Sets) and PPSs (Picture Parameter Sets).
+  /// 
+  /// Note that this function only supports extracting parameter
+  /// sets for streams with the H.265/HEVC codec and will return
+  /// `Error::UnsupportedCodecParameterSets` for streams with another
+  /// type of codec.
+  pub fn parameter_sets_hevc<'param>(
+    &'param self
+  ) -> Vec<Result<ParameterSetsHevc<'param>>> {
+    self.0.parameter_sets_hevc()
+  }
+
   /// Get the current RTP sequence number and timestamp.
   pub fn seq_and_timestamp(&self) -> (u16, u32) {
     rtp_seq_and_timestamp(&self.0.writer.output)
@@ -0,0 +1,527 @@
+use super::{
+  Error,
+  Sps,
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Represents a parsed H.264 Sequence Parameter Set (SPS) as defined in
+/// Section 7.3.2.1 in the Recommendation H.264. Only the fields that
+/// describe the stream as a whole are kept; fields that only matter for
+/// decoding are skipped.
+/// 
+/// # Examples
+/// 
+/// ```
+/// let muxer = Muxer::new_to_file(&PathBuf::from("my_video.mp4").into())
+///   .unwrap()
+///   .with_streams(&reader)
+///   .unwrap();
+/// for parameter_sets in muxer.parameter_sets_h264() {
+///   let (sps, _) = parameter_sets.unwrap();
+///   let sps = SequenceParameterSet::parse(sps).unwrap();
+///   println!("{}x{} at level {}", sps.width, sps.height, sps.level_idc);
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceParameterSet {
+  /// Profile, like 66 (Baseline), 77 (Main) or 100 (High).
+  pub profile_idc: u8,
+  /// Byte holding `constraint_set0_flag` (most significant bit) through
+  /// `constraint_set5_flag` and two reserved bits.
+  pub constraint_set_flags: u8,
+  /// Level multiplied by ten, like 31 for level 3.1.
+  pub level_idc: u8,
+  /// Identifier that picture parameter sets refer to.
+  pub seq_parameter_set_id: u32,
+  /// Chroma format: 0 (monochrome), 1 (4:2:0), 2 (4:2:2) or 3 (4:4:4).
+  pub chroma_format_idc: u32,
+  /// Whether or not the three colour components of 4:4:4 are coded
+  /// separately.
+  pub separate_colour_plane: bool,
+  /// Bit depth of luma samples.
+  pub bit_depth_luma: u32,
+  /// Bit depth of chroma samples.
+  pub bit_depth_chroma: u32,
+  /// Maximum number of reference frames.
+  pub max_num_ref_frames: u32,
+  /// Whether or not all pictures are frames (as opposed to fields).
+  pub frame_mbs_only: bool,
+  /// Width in pixels before cropping, a multiple of the macroblock size.
+  pub coded_width: u32,
+  /// Height in pixels before cropping, a multiple of the macroblock size.
+  pub coded_height: u32,
+  /// Width in pixels with the frame cropping rectangle applied.
+  pub width: u32,
+  /// Height in pixels with the frame cropping rectangle applied.
+  pub height: u32,
+  /// Timing information from the VUI (Video Usability Information), if
+  /// present.
+  pub timing: Option<VuiTiming>,
+}
+
+impl SequenceParameterSet {
+
+  /// Parse an SPS NAL unit, including its NAL unit header, as returned
+  /// by `extract_parameter_sets_h264`.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `sps` - SPS NAL unit bytes.
+  pub fn parse(sps: Sps) -> Result<Self> {
+    if sps.is_empty() || sps[0] & 0x1f != 0x07 {
+      return Err(Error::InvalidExtraData);
+    }
+
+    let rbsp = remove_emulation_prevention(&sps[1..]);
+    let mut reader = BitReader::new(&rbsp);
+
+    let profile_idc = reader.read_bits(8)? as u8;
+    let constraint_set_flags = reader.read_bits(8)? as u8;
+    let level_idc = reader.read_bits(8)? as u8;
+    let seq_parameter_set_id = reader.read_ue()?;
+
+    let mut chroma_format_idc = 1;
+    let mut separate_colour_plane = false;
+    let mut bit_depth_luma = 8;
+    let mut bit_depth_chroma = 8;
+    // Only the high profiles signal chroma format and bit depth.
+    if matches!(
+      profile_idc,
+      100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135
+    ) {
+      chroma_format_idc = reader.read_ue()?;
+      if chroma_format_idc > 3 {
+        return Err(Error::InvalidExtraData);
+      }
+      if chroma_format_idc == 3 {
+        separate_colour_plane = reader.read_bit()?;
+      }
+      let bit_depth_luma_minus8 = reader.read_ue()?;
+      let bit_depth_chroma_minus8 = reader.read_ue()?;
+      if bit_depth_luma_minus8 > 6 || bit_depth_chroma_minus8 > 6 {
+        return Err(Error::InvalidExtraData);
+      }
+      bit_depth_luma = bit_depth_luma_minus8 + 8;
+      bit_depth_chroma = bit_depth_chroma_minus8 + 8;
+      let _qpprime_y_zero_transform_bypass = reader.read_bit()?;
+      let seq_scaling_matrix_present = reader.read_bit()?;
+      if seq_scaling_matrix_present {
+        let num_scaling_lists = if chroma_format_idc != 3 { 8 } else { 12 };
+        for i in 0..num_scaling_lists {
+          let seq_scaling_list_present = reader.read_bit()?;
+          if seq_scaling_list_present {
+            reader.skip_scaling_list(if i < 6 { 16 } else { 64 })?;
+          }
+        }
+      }
+    }
+
+    let _log2_max_frame_num_minus4 = reader.read_ue()?;
+    let pic_order_cnt_type = reader.read_ue()?;
+    match pic_order_cnt_type {
+      0 => {
+        let _log2_max_pic_order_cnt_lsb_minus4 = reader.read_ue()?;
+      },
+      1 => {
+        let _delta_pic_order_always_zero = reader.read_bit()?;
+        let _offset_for_non_ref_pic = reader.read_se()?;
+        let _offset_for_top_to_bottom_field = reader.read_se()?;
+        let num_ref_frames_in_pic_order_cnt_cycle = reader.read_ue()?;
+        for _ in 0..num_ref_frames_in_pic_order_cnt_cycle {
+          let _offset_for_ref_frame = reader.read_se()?;
+        }
+      },
+      _ => {},
+    }
+
+    let max_num_ref_frames = reader.read_ue()?;
+    let _gaps_in_frame_num_value_allowed = reader.read_bit()?;
+    let pic_width_in_mbs = reader
+      .read_ue()?
+      .checked_add(1)
+      .ok_or(Error::InvalidExtraData)?;
+    let pic_height_in_map_units = reader
+      .read_ue()?
+      .checked_add(1)
+      .ok_or(Error::InvalidExtraData)?;
+    let frame_mbs_only = reader.read_bit()?;
+    if !frame_mbs_only {
+      let _mb_adaptive_frame_field = reader.read_bit()?;
+    }
+    let _direct_8x8_inference = reader.read_bit()?;
+
+    let coded_width = pic_width_in_mbs
+      .checked_mul(16)
+      .ok_or(Error::InvalidExtraData)?;
+    let coded_height = pic_height_in_map_units
+      .checked_mul(if frame_mbs_only { 16 } else { 32 })
+      .ok_or(Error::InvalidExtraData)?;
+
+    let mut width = coded_width;
+    let mut height = coded_height;
+    let frame_cropping = reader.read_bit()?;
+    if frame_cropping {
+      let crop_left = reader.read_ue()?;
+      let crop_right = reader.read_ue()?;
+      let crop_top = reader.read_ue()?;
+      let crop_bottom = reader.read_ue()?;
+
+      // Crop offsets are expressed in chroma sample units (Section
+      // 7.4.2.1.1), which depend on the chroma format.
+      let chroma_array_type = if separate_colour_plane {
+        0
+      } else {
+        chroma_format_idc
+      };
+      let (crop_unit_x, crop_unit_y) = match chroma_array_type {
+        1 => (2, 2),
+        2 => (2, 1),
+        _ => (1, 1),
+      };
+      let crop_unit_y = crop_unit_y * if frame_mbs_only { 1 } else { 2 };
+
+      width = crop_left
+        .checked_add(crop_right)
+        .and_then(|crop| crop.checked_mul(crop_unit_x))
+        .and_then(|crop| width.checked_sub(crop))
+        .ok_or(Error::InvalidExtraData)?;
+      height = crop_top
+        .checked_add(crop_bottom)
+        .and_then(|crop| crop.checked_mul(crop_unit_y))
+        .and_then(|crop| height.checked_sub(crop))
+        .ok_or(Error::InvalidExtraData)?;
+    }
+
+    let vui_parameters_present = reader.read_bit()?;
+    let timing = if vui_parameters_present {
+      reader.read_vui_timing()?
+    } else {
+      None
+    };
+
+    Ok(Self {
+      profile_idc,
+      constraint_set_flags,
+      level_idc,
+      seq_parameter_set_id,
+      chroma_format_idc,
+      separate_colour_plane,
+      bit_depth_luma,
+      bit_depth_chroma,
+      max_num_ref_frames,
+      frame_mbs_only,
+      coded_width,
+      coded_height,
+      width,
+      height,
+      timing,
+    })
+  }
+
+  /// Whether or not a constraint set flag is set.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `index` - Index of constraint set flag, from 0 through 5.
+  pub fn constraint_set(&self, index: u8) -> bool {
+    index < 6 && self.constraint_set_flags & (0x80 >> index) != 0
+  }
+
+}
+
+/// Timing information from the VUI (Video Usability Information) part of
+/// a sequence parameter set, as defined in Annex E in the Recommendation
+/// H.264.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VuiTiming {
+  /// Number of time units of a clock tick.
+  pub num_units_in_tick: u32,
+  /// Number of time units in one second.
+  pub time_scale: u32,
+  /// Whether or not the stream has a fixed frame rate.
+  pub fixed_frame_rate: bool,
+}
+
+impl VuiTiming {
+
+  /// Get the frame rate as numerator and denominator. A frame takes two
+  /// clock ticks (one for each field), so this is `time_scale` over twice
+  /// `num_units_in_tick`. Returns `None` if the timing is invalid.
+  pub fn frame_rate(&self) -> Option<(u32, u32)> {
+    let denominator = self.num_units_in_tick.checked_mul(2)?;
+    if self.time_scale > 0 && denominator > 0 {
+      Some((self.time_scale, denominator))
+    } else {
+      None
+    }
+  }
+
+}
+
+/// Convert NAL unit payload to RBSP (Raw Byte Sequence Payload) by
+/// removing the emulation prevention bytes: every `0x03` that follows two
+/// zero bytes was inserted by the encoder to avoid start codes inside the
+/// payload.
+/// 
+/// # Arguments
+/// 
+/// * `bytes` - NAL unit payload, without NAL unit header.
+pub(crate) fn remove_emulation_prevention(bytes: &[u8]) -> Vec<u8> {
+  let mut rbsp = Vec::with_capacity(bytes.len());
+  let mut zeros = 0;
+  for &byte in bytes {
+    if zeros >= 2 && byte == 0x03 {
+      zeros = 0;
+      continue;
+    }
+
+    zeros = if byte == 0x00 { zeros + 1 } else { 0 };
+    rbsp.push(byte);
+  }
+
+  rbsp
+}
+
+/// Reads bits, most significant bit first, from an RBSP. All reads fail
+/// with `Error::InvalidExtraData` when reading past the end.
+pub(crate) struct BitReader<'buf> {
+  bytes: &'buf [u8],
+  position: usize,
+}
+
+impl<'buf> BitReader<'buf> {
+
+  /// Create a reader that starts at the first bit.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `bytes` - RBSP bytes to read.
+  pub(crate) fn new(bytes: &'buf [u8]) -> Self {
+    Self {
+      bytes,
+      position: 0,
+    }
+  }
+
+  /// Read a single bit as flag.
+  pub(crate) fn read_bit(&mut self) -> Result<bool> {
+    let byte = self.bytes
+      .get(self.position / 8)
+      .ok_or(Error::InvalidExtraData)?;
+    let bit = (byte >> (7 - self.position % 8)) & 0x01;
+    self.position += 1;
+    Ok(bit == 1)
+  }
+
+  /// Read a fixed number of bits as unsigned integer.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `count` - Number of bits, at most 32.
+  pub(crate) fn read_bits(&mut self, count: u32) -> Result<u32> {
+    let mut value = 0_u32;
+    for _ in 0..count {
+      value = (value << 1) | self.read_bit()? as u32;
+    }
+    Ok(value)
+  }
+
+  /// Skip a number of bits.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `count` - Number of bits to skip.
+  pub(crate) fn skip_bits(&mut self, count: usize) -> Result<()> {
+    if self.position + count > self.bytes.len() * 8 {
+      return Err(Error::InvalidExtraData);
+    }
+    self.position += count;
+    Ok(())
+  }
+
+  /// Read an unsigned Exp-Golomb-coded integer (`ue(v)`).
+  pub(crate) fn read_ue(&mut self) -> Result<u32> {
+    let mut leading_zeros = 0;
+    while !self.read_bit()? {
+      leading_zeros += 1;
+      if leading_zeros > 31 {
+        return Err(Error::InvalidExtraData);
+      }
+    }
+
+    let suffix = self.read_bits(leading_zeros)? as u64;
+    u32::try_from((1_u64 << leading_zeros) - 1 + suffix)
+      .map_err(|_| Error::InvalidExtraData)
+  }
+
+  /// Read a signed Exp-Golomb-coded integer (`se(v)`).
+  pub(crate) fn read_se(&mut self) -> Result<i32> {
+    let value = self.read_ue()? as i64;
+    Ok(if value % 2 == 1 {
+      ((value + 1) / 2) as i32
+    } else {
+      -(value / 2) as i32
+    })
+  }
+
+  /// Skip a scaling list (Section 7.3.2.1.1.1). Its size is implied by the
+  /// values in it, so it must be parsed to skip it.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `size` - Number of coefficients in list.
+  fn skip_scaling_list(&mut self, size: usize) -> Result<()> {
+    let mut last_scale = 8;
+    let mut next_scale = 8;
+    for _ in 0..size {
+      if next_scale != 0 {
+        let delta_scale = self.read_se()?;
+        if !(-128..=127).contains(&delta_scale) {
+          return Err(Error::InvalidExtraData);
+        }
+        next_scale = (last_scale + delta_scale + 256).rem_euclid(256);
+      }
+      if next_scale != 0 {
+        last_scale = next_scale;
+      }
+    }
+    Ok(())
+  }
+
+  /// Read the VUI parameters up to and including the timing information
+  /// (Section E.1.1). The remainder of the VUI parameters is ignored.
+  fn read_vui_timing(&mut self) -> Result<Option<VuiTiming>> {
+    const EXTENDED_SAR: u32 = 255;
+
+    let aspect_ratio_info_present = self.read_bit()?;
+    if aspect_ratio_info_present {
+      let aspect_ratio_idc = self.read_bits(8)?;
+      if aspect_ratio_idc == EXTENDED_SAR {
+        let _sar_width = self.read_bits(16)?;
+        let _sar_height = self.read_bits(16)?;
+      }
+    }
+
+    let overscan_info_present = self.read_bit()?;
+    if overscan_info_present {
+      let _overscan_appropriate = self.read_bit()?;
+    }
+
+    let video_signal_type_present = self.read_bit()?;
+    if video_signal_type_present {
+      let _video_format = self.read_bits(3)?;
+      let _video_full_range = self.read_bit()?;
+      let colour_description_present = self.read_bit()?;
+      if colour_description_present {
+        let _colour_primaries = self.read_bits(8)?;
+        let _transfer_characteristics = self.read_bits(8)?;
+        let _matrix_coefficients = self.read_bits(8)?;
+      }
+    }
+
+    let chroma_loc_info_present = self.read_bit()?;
+    if chroma_loc_info_present {
+      let _chroma_sample_loc_type_top_field = self.read_ue()?;
+      let _chroma_sample_loc_type_bottom_field = self.read_ue()?;
+    }
+
+    let timing_info_present = self.read_bit()?;
+    if timing_info_present {
+      Ok(Some(VuiTiming {
+        num_units_in_tick: self.read_bits(32)?,
+        time_scale: self.read_bits(32)?,
+        fixed_frame_rate: self.read_bit()?,
+      }))
+    } else {
+      Ok(None)
+    }
+  }
+
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// High profile, level 3.1, 1920x1080 at 25 frames per second. Contains
+  /// emulation prevention bytes in the VUI timing information.
+  const SPS_HIGH_1080P25: &[u8] = &[
+    0x67, 0x64, 0x00, 0x1f, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0x84,
+    0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xca, 0x10,
+  ];
+
+  /// Constrained baseline profile, level 3.0, 640x360 without VUI.
+  const SPS_BASELINE_360P: &[u8] = &[
+    0x67, 0x42, 0xc0, 0x1e, 0xec, 0x80, 0x50, 0x17, 0xfc, 0xa8,
+  ];
+
+  #[test]
+  fn parse_high_profile_with_cropping_and_timing() {
+    let sps = SequenceParameterSet::parse(SPS_HIGH_1080P25).unwrap();
+    assert_eq!(sps.profile_idc, 100);
+    assert_eq!(sps.constraint_set_flags, 0x00);
+    assert_eq!(sps.level_idc, 31);
+    assert_eq!(sps.chroma_format_idc, 1);
+    assert_eq!(sps.bit_depth_luma, 8);
+    assert_eq!(sps.bit_depth_chroma, 8);
+    assert_eq!(sps.max_num_ref_frames, 4);
+    assert!(sps.frame_mbs_only);
+    assert_eq!((sps.coded_width, sps.coded_height), (1920, 1088));
+    assert_eq!((sps.width, sps.height), (1920, 1080));
+
+    let timing = sps.timing.unwrap();
+    assert_eq!(timing.num_units_in_tick, 1);
+    assert_eq!(timing.time_scale, 50);
+    assert!(timing.fixed_frame_rate);
+    assert_eq!(timing.frame_rate(), Some((50, 2)));
+  }
+
+  #[test]
+  fn parse_baseline_profile_without_vui() {
+    let sps = SequenceParameterSet::parse(SPS_BASELINE_360P).unwrap();
+    assert_eq!(sps.profile_idc, 66);
+    assert!(sps.constraint_set(0));
+    assert!(sps.constraint_set(1));
+    assert!(!sps.constraint_set(2));
+    assert_eq!(sps.level_idc, 30);
+    assert_eq!(sps.max_num_ref_frames, 3);
+    assert_eq!((sps.coded_width, sps.coded_height), (640, 368));
+    assert_eq!((sps.width, sps.height), (640, 360));
+    assert_eq!(sps.timing, None);
+  }
+
+  #[test]
+  fn parse_rejects_invalid() {
+    assert!(SequenceParameterSet::parse(&[]).is_err());
+    // PPS instead of SPS.
+    assert!(SequenceParameterSet::parse(&[0x68, 0xeb, 0xe3, 0xcb]).is_err());
+    // Truncated.
+    assert!(SequenceParameterSet::parse(&SPS_HIGH_1080P25[..8]).is_err());
+  }
+
+  #[test]
+  fn remove_emulation_prevention_bytes() {
+    assert_eq!(
+      remove_emulation_prevention(&[
+        0x00, 0x00, 0x03, 0x01, 0x00, 0x00, 0x03, 0x03, 0x00, 0x03]),
+      [0x00, 0x00, 0x01, 0x00, 0x00, 0x03, 0x00, 0x03]);
+    // A three that does not follow two zeros is kept.
+    assert_eq!(
+      remove_emulation_prevention(&[0x00, 0x03, 0x00, 0x00, 0x00]),
+      [0x00, 0x03, 0x00, 0x00, 0x00]);
+  }
+
+  #[test]
+  fn bit_reader_exp_golomb() {
+    // 1 | 010 | 011 | 00101 | 00110 | 0000000
+    let mut reader = BitReader::new(&[0b1010_0110, 0b0101_0011, 0b0000_0000]);
+    assert_eq!(reader.read_ue().unwrap(), 0);
+    assert_eq!(reader.read_ue().unwrap(), 1);
+    assert_eq!(reader.read_ue().unwrap(), 2);
+    assert_eq!(reader.read_se().unwrap(), -2);
+    assert_eq!(reader.read_se().unwrap(), 3);
+    assert!(reader.read_ue().is_err());
+  }
+
+}
@@ -9,6 +9,7 @@ use ffmpeg::{
 use super::{
   io::Reader,
   Error,
+  codec_string::codec_string,
 };
 
 type Result<T> = std::result::Result<T, Error>;
@@ -46,6 +47,46 @@ impl StreamInfo {
     })
   }
 
+  /// Create stream information from its parts.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `index` - Stream index.
+  /// * `codec_parameters` - Codec parameters.
+  /// * `time_base` - Stream time base.
+  pub(crate) fn new(
+    index: usize,
+    codec_parameters: AvCodecParameters,
+    time_base: AvRational,
+  ) -> Self {
+    Self {
+      index,
+      codec_parameters,
+      time_base,
+    }
+  }
+
+  /// Get the codec string as defined in RFC 6381, like `avc1.64001f` or
+  /// `mp4a.40.2`, for use in the `codecs` parameter of a MIME type (for
+  /// example with Media Source Extensions).
+  /// 
+  /// Note that this function only supports H.264, H.265/HEVC, VP9, AV1,
+  /// AAC and Opus streams and will return `Error::UnsupportedCodecString`
+  /// for streams with another type of codec.
+  pub fn codec_string(&self) -> Result<String> {
+    codec_string(&self.codec_parameters)
+  }
+
+  /// Get the codec parameters.
+  pub(crate) fn codec_parameters(&self) -> &AvCodecParameters {
+    &self.codec_parameters
+  }
+
+  /// Get the stream time base.
+  pub(crate) fn time_base(&self) -> AvRational {
+    self.time_base
+  }
+
   /// Turn information back into parts for usage.
   /// 
   /// Note: Consumes stream information object.
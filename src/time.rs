@@ -1,5 +1,8 @@
 extern crate ffmpeg_next as ffmpeg;
 
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
 use std::time::Duration;
 
 use ffmpeg::{
@@ -7,10 +10,16 @@ use ffmpeg::{
   util::mathematics::rescale::Rescale,
 };
 
+use super::Error;
+
 /// Represents a frame timestamp relative to the start of original
 /// stream. This can be either a presentation timestamp (PTS), decoder
 /// timestamp (DTS) or even a duration.
-#[derive(Clone, Debug)]
+/// 
+/// Timestamps can be added, subtracted and compared regardless of their
+/// time base. A timestamp without value orders before any timestamp with
+/// a value, and arithmetic involving it produces a timestamp without value.
+#[derive(Clone, Copy, Debug)]
 pub struct Time {
   time: Option<i64>,
   time_base: AvRational,
@@ -20,10 +29,108 @@ impl Time {
 
   /// Create a new zero-valued timestamp.
   pub fn zero() -> Self {
-    Time {
-      time: Some(0),
-      time_base: (1, 90000).into(),
+    Self::zero_with_time_base((1, 90000))
+  }
+
+  /// Create a new zero-valued timestamp in the given time base.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `time_base` - Time base as numerator and denominator.
+  pub fn zero_with_time_base(time_base: (i32, i32)) -> Self {
+    Self::from_value(0, time_base)
+  }
+
+  /// Create a new timestamp from a `Duration`, expressed in the given time
+  /// base. Rounds to the nearest value in the time base. If the time base
+  /// is not positive, the timestamp has no value.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `duration` - Duration to convert.
+  /// * `time_base` - Time base as numerator and denominator.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// // Express one second in a 48 kHz audio time base.
+  /// let time = Time::from_duration(Duration::from_secs(1), (1, 48000));
+  /// assert_eq!(time.value(), Some(48000));
+  /// ```
+  pub fn from_duration(duration: Duration, time_base: (i32, i32)) -> Self {
+    const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+    let (numerator, denominator) = normalized(time_base.into());
+    if numerator <= 0 || denominator == 0 {
+      return Self::none(time_base);
     }
+
+    // value = nanos / 10^9 / (num / den), rounded to nearest.
+    let dividend = duration.as_nanos() as i128 * denominator;
+    let divisor = numerator * NANOS_PER_SEC;
+    let value = (2 * dividend + divisor) / (2 * divisor);
+
+    Self::from_value(value as i64, time_base)
+  }
+
+  /// Create a new timestamp from a whole number of seconds.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `secs` - Number of seconds.
+  pub fn from_secs(secs: i64) -> Self {
+    Self::from_value(secs, (1, 1))
+  }
+
+  /// Create a new timestamp from a fractional number of seconds. The
+  /// timestamp is expressed in a time base of 1/90000.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `secs` - Number of seconds.
+  pub fn from_secs_f64(secs: f64) -> Self {
+    const TIMEBASE: (i32, i32) = (1, 90000);
+
+    Self::from_value(
+      (secs * (TIMEBASE.1 as f64)).round() as i64,
+      TIMEBASE)
+  }
+
+  /// Create a new timestamp from a number of milliseconds.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `millis` - Number of milliseconds.
+  pub fn from_millis(millis: i64) -> Self {
+    Self::from_value(millis, (1, 1000))
+  }
+
+  /// Create a new timestamp from its value and the time base in which
+  /// the value is expressed.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `value` - Relative time in `time_base` units.
+  /// * `time_base` - Time base as numerator and denominator.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// // Three frames at 25 fps.
+  /// let time = Time::from_value(3, (1, 25));
+  /// assert_eq!(time, Time::from_millis(120));
+  /// ```
+  pub fn from_value(value: i64, time_base: (i32, i32)) -> Self {
+    Self::new(Some(value), time_base.into())
+  }
+
+  /// Create a new timestamp that has no value.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `time_base` - Time base as numerator and denominator.
+  pub fn none(time_base: (i32, i32)) -> Self {
+    Self::new(None, time_base.into())
   }
 
   /// Create a new timestamp by its time value and time base in
@@ -49,6 +156,68 @@ impl Time {
     self.time.is_some()
   }
 
+  /// Get the value of the timestamp in units of its time base.
+  pub fn value(&self) -> Option<i64> {
+    self.time
+  }
+
+  /// Get the time base of the timestamp as numerator and denominator.
+  pub fn time_base(&self) -> (i32, i32) {
+    (self.time_base.numerator(), self.time_base.denominator())
+  }
+
+  /// Get the timestamp in seconds as floating-point value.
+  pub fn as_secs_f64(&self) -> Option<f64> {
+    self.time.map(|time| time as f64 * f64::from(self.time_base))
+  }
+
+  /// Get the timestamp as signed number of milliseconds. Unlike converting
+  /// to `Duration`, this preserves negative timestamps.
+  pub fn as_millis(&self) -> Option<i64> {
+    self.aligned((1, 1000).into()).time
+  }
+
+  /// Get the timestamp as signed number of microseconds. Unlike converting
+  /// to `Duration`, this preserves negative timestamps.
+  pub fn as_micros(&self) -> Option<i64> {
+    self.aligned((1, 1_000_000).into()).time
+  }
+
+  /// Convert to a Rust-native `Duration`. Unlike the `From` conversion,
+  /// this fails with `Error::MissingTimestamp` if the timestamp has no value
+  /// and with `Error::NegativeTimestamp` if it is negative.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// let dts = packet.dts();
+  /// match dts.to_duration() {
+  ///   Ok(duration) => println!("dts: {:?}", duration),
+  ///   Err(Error::NegativeTimestamp) => println!("negative dts: {}", dts),
+  ///   Err(_) => println!("no dts"),
+  /// }
+  /// ```
+  pub fn to_duration(&self) -> Result<Duration, Error> {
+    match self.as_micros() {
+      Some(micros) if micros >= 0 =>
+        Ok(Duration::from_micros(micros as u64)),
+      Some(_) =>
+        Err(Error::NegativeTimestamp),
+      None =>
+        Err(Error::MissingTimestamp),
+    }
+  }
+
+  /// Convert the timestamp to another time base. Rounds to the nearest
+  /// value in the new time base.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `time_base` - Target time base as numerator and denominator.
+  pub fn with_time_base(&self, time_base: (i32, i32)) -> Time {
+    self.aligned(time_base.into())
+  }
+
   /// Align the timestamp with another timestamp, which will convert
   /// the `rhs` timestamp to the same time base, such that operations
   /// can be performed upon the aligned timestamps.
@@ -62,7 +231,7 @@ impl Time {
   /// Two timestamps that are aligned.
   pub fn aligned_with(&self, rhs: &Time) -> Aligned {
     Aligned {
-      lhs: self.time.clone(),
+      lhs: self.time,
       rhs: rhs
         .time
         .map(|rhs_time| rhs_time.rescale(rhs.time_base, self.time_base)),
@@ -92,26 +261,164 @@ impl Time {
 
 }
 
-impl From<Duration> for Time {
+impl Add for Time {
+  type Output = Time;
 
-  /// Convert from a `Duration` to `Time`.
-  fn from(duration: Duration) -> Self {
-    const TIMEBASE: (i32, i32) = (1, 90000);
+  /// Add two timestamps. The result is expressed in the time base of the
+  /// left-hand side.
+  fn add(self, rhs: Time) -> Time {
+    self.aligned_with(&rhs).add()
+  }
 
-    let time = duration.as_secs_f64() * (TIMEBASE.1 as f64);
-    let time = time.round() as i64;
+}
 
-    Self {
-      time: Some(time),
-      time_base: TIMEBASE.into(),
+impl AddAssign for Time {
+
+  fn add_assign(&mut self, rhs: Time) {
+    *self = *self + rhs;
+  }
+
+}
+
+impl Sub for Time {
+  type Output = Time;
+
+  /// Subtract two timestamps. The result is expressed in the time base of
+  /// the left-hand side.
+  fn sub(self, rhs: Time) -> Time {
+    self.aligned_with(&rhs).subtract()
+  }
+
+}
+
+impl SubAssign for Time {
+
+  fn sub_assign(&mut self, rhs: Time) {
+    *self = *self - rhs;
+  }
+
+}
+
+impl Mul<(i32, i32)> for Time {
+  type Output = Time;
+
+  /// Multiply the timestamp by a rational number, given as numerator and
+  /// denominator. Rounds to the nearest value in the time base of the
+  /// timestamp, with halfway cases rounded away from zero. If the
+  /// denominator is zero or the result does not fit, the result has no
+  /// value.
+  fn mul(self, (numerator, denominator): (i32, i32)) -> Time {
+    let (numerator, denominator) =
+      normalized(AvRational::new(numerator, denominator));
+
+    Time {
+      time: self
+        .time
+        .filter(|_| denominator != 0)
+        .and_then(|time| {
+          let product = time as i128 * numerator;
+          let value =
+            (2 * product + product.signum() * denominator) / (2 * denominator);
+          i64::try_from(value).ok()
+        }),
+      time_base: self.time_base,
+    }
+  }
+
+}
+
+impl PartialEq for Time {
+
+  fn eq(&self, other: &Time) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+
+}
+
+impl Eq for Time {}
+
+impl PartialOrd for Time {
+
+  fn partial_cmp(&self, other: &Time) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+
+}
+
+impl Ord for Time {
+
+  /// Compare timestamps exactly, regardless of their time bases. A
+  /// timestamp without value is smaller than any timestamp with value.
+  fn cmp(&self, other: &Time) -> Ordering {
+    match (self.time, other.time) {
+      (Some(lhs), Some(rhs)) => {
+        // Cross-multiply to compare `lhs * lhs_tb` and `rhs * rhs_tb`
+        // without losing precision.
+        let (lhs_num, lhs_den) = normalized(self.time_base);
+        let (rhs_num, rhs_den) = normalized(other.time_base);
+        (lhs as i128 * lhs_num * rhs_den)
+          .cmp(&(rhs as i128 * rhs_num * lhs_den))
+      },
+      (lhs, rhs) => lhs.is_some().cmp(&rhs.is_some()),
+    }
+  }
+
+}
+
+impl fmt::Display for Time {
+
+  /// Format timestamp as `HH:MM:SS.mmm`, or `N/A` if it has no value.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.aligned((1, 1000).into()).time {
+      Some(millis) => {
+        let sign = if millis < 0 { "-" } else { "" };
+        let millis = millis.unsigned_abs();
+        write!(
+          f,
+          "{}{:02}:{:02}:{:02}.{:03}",
+          sign,
+          millis / 3_600_000,
+          (millis / 60_000) % 60,
+          (millis / 1000) % 60,
+          millis % 1000,
+        )
+      },
+      None => {
+        write!(f, "N/A")
+      },
     }
   }
 
 }
 
+/// Get numerator and denominator of time base with a positive denominator.
+fn normalized(time_base: AvRational) -> (i128, i128) {
+  let numerator = time_base.numerator() as i128;
+  let denominator = time_base.denominator() as i128;
+  if denominator < 0 {
+    (-numerator, -denominator)
+  } else {
+    (numerator, denominator)
+  }
+}
+
+impl From<Duration> for Time {
+
+  /// Convert from a `Duration` to `Time` in a time base of 1/90000. Use
+  /// `Time::from_duration` to choose another time base.
+  fn from(duration: Duration) -> Self {
+    Self::from_duration(duration, (1, 90000))
+  }
+
+}
+
 impl From<Time> for Duration {
 
   /// Convert from a `Time` to a Rust-native `Duration`.
+  /// 
+  /// Note: Negative timestamps are clamped to zero and timestamps without
+  /// value are converted to `Duration::ZERO`. Use `Time::to_duration` to
+  /// detect these cases.
   fn from(timestamp: Time) -> Self {
     if let Some(offset) = timestamp.time {
       let micros = offset
@@ -138,26 +445,27 @@ pub struct Aligned {
 
 impl Aligned {
 
-  /// Add two timestamps together.
+  /// Add two timestamps together. If the result overflows, it has no
+  /// value.
   pub fn add(self) -> Time {
-    self.apply(|lhs, rhs| lhs + rhs)
+    self.apply(i64::checked_add)
   }
 
   /// Subtract the right-hand side timestamp from the left-hand
-  /// side timestamp.
+  /// side timestamp. If the result overflows, it has no value.
   pub fn subtract(self) -> Time {
-    self.apply(|lhs, rhs| lhs - rhs)
+    self.apply(i64::checked_sub)
   }
 
   /// Apply operation `f` on aligned timestamps.
   fn apply<F>(self, f: F) -> Time
   where
-    F: FnOnce(i64, i64) -> i64
+    F: FnOnce(i64, i64) -> Option<i64>
   {
     match (self.lhs, self.rhs) {
       (Some(lhs_time), Some(rhs_time)) => {
         Time {
-          time: Some(f(lhs_time, rhs_time)),
+          time: f(lhs_time, rhs_time),
           time_base: self.time_base,
         }
       },
@@ -170,4 +478,97 @@ impl Aligned {
     }
   }
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ordering_across_time_bases() {
+    assert_eq!(Time::from_value(1, (1, 25)), Time::from_millis(40));
+    assert_eq!(
+      Time::from_value(3, (1, 25)),
+      Time::from_value(3600, (1, 30000)));
+    assert!(Time::from_millis(39) < Time::from_value(1, (1, 25)));
+    assert!(Time::from_value(-1, (1, 90000)) < Time::zero());
+    assert!(Time::from_secs(1) > Time::from_value(89999, (1, 90000)));
+    // Time bases with a negative denominator are normalized.
+    assert_eq!(Time::from_value(-2, (1, -2)), Time::from_secs(1));
+  }
+
+  #[test]
+  fn ordering_without_value() {
+    assert!(Time::none((1, 1000)) < Time::from_secs(-100));
+    assert_eq!(Time::none((1, 1000)), Time::none((1, 25)));
+    assert!(Time::from_secs(0) > Time::none((1, 90000)));
+  }
+
+  #[test]
+  fn display() {
+    assert_eq!(Time::from_millis(3_723_004).to_string(), "01:02:03.004");
+    assert_eq!(Time::from_millis(-1500).to_string(), "-00:00:01.500");
+    assert_eq!(Time::from_value(1, (1, 25)).to_string(), "00:00:00.040");
+    assert_eq!(Time::from_secs(100 * 3600).to_string(), "100:00:00.000");
+    assert_eq!(Time::none((1, 1000)).to_string(), "N/A");
+  }
+
+  #[test]
+  fn add_and_subtract() {
+    let time = Time::from_millis(1500) + Time::from_secs(1);
+    assert_eq!(time.value(), Some(2500));
+    assert_eq!(time.time_base(), (1, 1000));
+
+    let time = Time::from_millis(500) - Time::from_secs(1);
+    assert_eq!(time.value(), Some(-500));
+
+    let mut time = Time::from_value(10, (1, 25));
+    time += Time::from_millis(80);
+    assert_eq!(time.value(), Some(12));
+    time -= Time::from_millis(480);
+    assert_eq!(time.value(), Some(0));
+
+    assert!(!(Time::from_secs(1) + Time::none((1, 1))).has_value());
+    assert!(!(Time::none((1, 1)) - Time::from_secs(1)).has_value());
+  }
+
+  #[test]
+  fn add_and_subtract_overflow() {
+    let max = Time::from_value(i64::MAX, (1, 1000));
+    let min = Time::from_value(i64::MIN, (1, 1000));
+    assert!(!(max + Time::from_millis(1)).has_value());
+    assert!(!(min - Time::from_millis(1)).has_value());
+    assert_eq!((max - Time::from_millis(1)).value(), Some(i64::MAX - 1));
+  }
+
+  #[test]
+  fn multiply() {
+    let time = Time::from_value(10, (1, 25));
+    assert_eq!((time * (3, 2)).value(), Some(15));
+    assert_eq!((time * (-1, 1)).value(), Some(-10));
+    assert_eq!((time * (1, -2)).value(), Some(-5));
+    assert_eq!((time * (0, 1)).value(), Some(0));
+    assert_eq!((time * (3, 2)).time_base(), (1, 25));
+    // Halfway cases are rounded away from zero.
+    assert_eq!((Time::from_value(5, (1, 25)) * (1, 2)).value(), Some(3));
+    assert_eq!((Time::from_value(-5, (1, 25)) * (1, 2)).value(), Some(-3));
+    assert_eq!((Time::from_value(4, (1, 25)) * (1, 3)).value(), Some(1));
+  }
+
+  #[test]
+  fn multiply_without_result() {
+    assert!(!(Time::from_secs(1) * (1, 0)).has_value());
+    assert!(!(Time::from_value(i64::MAX, (1, 1)) * (2, 1)).has_value());
+    assert!(!(Time::none((1, 1)) * (2, 1)).has_value());
+  }
+
+  #[test]
+  fn from_duration_rounds_to_nearest() {
+    let time = Time::from_duration(Duration::from_millis(1010), (1, 25));
+    assert_eq!(time.value(), Some(25));
+    let time = Time::from_duration(Duration::from_millis(1020), (1, 25));
+    assert_eq!(time.value(), Some(26));
+    assert!(!Time::from_duration(Duration::from_secs(1), (0, 1)).has_value());
+    assert!(!Time::from_duration(Duration::from_secs(1), (1, 0)).has_value());
+  }
+
+}
@@ -0,0 +1,418 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{
+  Error,
+  Time,
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Represents a SMPTE timecode in the form `HH:MM:SS:FF`, where `FF` is
+/// the frame number within the second. Drop-frame timecodes (written as
+/// `HH:MM:SS;FF`) skip the first frame numbers of each minute, except for
+/// every tenth minute, to stay in sync with the wall clock at NTSC frame
+/// rates like 29.97 and 59.94.
+/// 
+/// A timecode does not carry a frame rate itself. Conversions to and from
+/// frame numbers and `Time` take the frame rate as argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Timecode {
+  hours: u32,
+  minutes: u32,
+  seconds: u32,
+  frames: u32,
+  drop_frame: bool,
+}
+
+impl Timecode {
+
+  /// Name of the metadata tag that holds the timecode in containers.
+  pub(crate) const TAG: &'static str = "timecode";
+
+  /// Create a new timecode.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `hours` - Hours, below 24.
+  /// * `minutes` - Minutes, below 60.
+  /// * `seconds` - Seconds, below 60.
+  /// * `frames` - Frame within the second.
+  /// * `drop_frame` - Whether or not this is a drop-frame timecode.
+  pub fn new(
+    hours: u32,
+    minutes: u32,
+    seconds: u32,
+    frames: u32,
+    drop_frame: bool,
+  ) -> Result<Self> {
+    if hours >= 24 || minutes >= 60 || seconds >= 60 {
+      return Err(Error::InvalidTimecode);
+    }
+
+    // In drop-frame timecode, the first frame numbers of every minute that
+    // is not a multiple of ten do not exist. This depends on the frame rate,
+    // so only the frame numbers that are dropped at any rate are rejected
+    // here. The rest is checked by `to_frame_number`.
+    if drop_frame && is_drop_minute(seconds, minutes) && frames < 2 {
+      return Err(Error::InvalidTimecode);
+    }
+
+    Ok(Self {
+      hours,
+      minutes,
+      seconds,
+      frames,
+      drop_frame,
+    })
+  }
+
+  /// Create a timecode from a frame number, counting from `00:00:00:00`.
+  /// Timecodes wrap around after 24 hours.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame_number` - Number of frame.
+  /// * `frame_rate` - Frame rate as numerator and denominator.
+  /// * `drop_frame` - Whether or not to produce a drop-frame timecode.
+  pub fn from_frame_number(
+    frame_number: u64,
+    frame_rate: (i32, i32),
+    drop_frame: bool,
+  ) -> Result<Self> {
+    let fps = nominal_frame_rate(frame_rate, drop_frame)?;
+
+    let frame_number = if drop_frame {
+      let drop = drop_frames_per_minute(fps);
+      let frames_per_ten_minutes = fps * 60 * 10 - drop * 9;
+      let frames_per_minute = fps * 60 - drop;
+
+      // A drop-frame day has fewer frames than a nominal day, since frame
+      // numbers are dropped.
+      let frame_number = frame_number % (frames_per_ten_minutes * 6 * 24);
+      let ten_minutes = frame_number / frames_per_ten_minutes;
+      let remainder = frame_number % frames_per_ten_minutes;
+      // Add back the frame numbers that were dropped so far, such that the
+      // result can be decomposed like a non-drop-frame timecode.
+      let dropped = drop * 9 * ten_minutes +
+        if remainder > drop {
+          drop * ((remainder - drop) / frames_per_minute)
+        } else {
+          0
+        };
+
+      frame_number + dropped
+    } else {
+      frame_number % (fps * 60 * 60 * 24)
+    };
+
+    Ok(Self {
+      hours: ((frame_number / (fps * 60 * 60)) % 24) as u32,
+      minutes: ((frame_number / (fps * 60)) % 60) as u32,
+      seconds: ((frame_number / fps) % 60) as u32,
+      frames: (frame_number % fps) as u32,
+      drop_frame,
+    })
+  }
+
+  /// Convert the timecode to a frame number, counting from `00:00:00:00`.
+  /// Fails with `Error::InvalidTimecode` if the timecode does not exist at
+  /// the frame rate, like a frame number that is dropped in drop-frame
+  /// timecode.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame_rate` - Frame rate as numerator and denominator.
+  pub fn to_frame_number(self, frame_rate: (i32, i32)) -> Result<u64> {
+    let fps = nominal_frame_rate(frame_rate, self.drop_frame)?;
+    if self.frames as u64 >= fps {
+      return Err(Error::InvalidTimecode);
+    }
+
+    if self.drop_frame &&
+       is_drop_minute(self.seconds, self.minutes) &&
+       (self.frames as u64) < drop_frames_per_minute(fps) {
+      return Err(Error::InvalidTimecode);
+    }
+
+    let total_minutes = 60 * self.hours as u64 + self.minutes as u64;
+    let frame_number =
+      (total_minutes * 60 + self.seconds as u64) * fps + self.frames as u64;
+
+    if self.drop_frame {
+      let drop = drop_frames_per_minute(fps);
+      Ok(frame_number - drop * (total_minutes - total_minutes / 10))
+    } else {
+      Ok(frame_number)
+    }
+  }
+
+  /// Create a timecode from a timestamp. The timestamp is rounded to the
+  /// nearest frame.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `time` - Timestamp, relative to `00:00:00:00`.
+  /// * `frame_rate` - Frame rate as numerator and denominator.
+  /// * `drop_frame` - Whether or not to produce a drop-frame timecode.
+  pub fn from_time(
+    time: &Time,
+    frame_rate: (i32, i32),
+    drop_frame: bool,
+  ) -> Result<Self> {
+    let (numerator, denominator) = frame_rate;
+    match time.with_time_base((denominator, numerator)).value() {
+      Some(frame_number) if frame_number >= 0 =>
+        Self::from_frame_number(frame_number as u64, frame_rate, drop_frame),
+      Some(_) =>
+        Err(Error::NegativeTimestamp),
+      None =>
+        Err(Error::MissingTimestamp),
+    }
+  }
+
+  /// Convert the timecode to a timestamp relative to `00:00:00:00`. The
+  /// timestamp is expressed in a time base of one frame.
+  /// 
+  /// # Arguments
+  /// 
+  /// * `frame_rate` - Frame rate as numerator and denominator.
+  pub fn to_time(self, frame_rate: (i32, i32)) -> Result<Time> {
+    let (numerator, denominator) = frame_rate;
+    Ok(Time::from_value(
+      self.to_frame_number(frame_rate)? as i64,
+      (denominator, numerator)))
+  }
+
+  /// Hours component.
+  pub fn hours(&self) -> u32 {
+    self.hours
+  }
+
+  /// Minutes component.
+  pub fn minutes(&self) -> u32 {
+    self.minutes
+  }
+
+  /// Seconds component.
+  pub fn seconds(&self) -> u32 {
+    self.seconds
+  }
+
+  /// Frames component.
+  pub fn frames(&self) -> u32 {
+    self.frames
+  }
+
+  /// Whether or not this is a drop-frame timecode.
+  pub fn is_drop_frame(&self) -> bool {
+    self.drop_frame
+  }
+
+}
+
+impl FromStr for Timecode {
+  type Err = Error;
+
+  /// Parse a timecode like `01:00:00:00`, or `01:00:00;00` for drop-frame.
+  /// Drop-frame timecodes may also use `.` or `,` as last separator.
+  fn from_str(s: &str) -> Result<Self> {
+    let separators = s
+      .char_indices()
+      .filter(|(_, c)| !c.is_ascii_digit())
+      .collect::<Vec<_>>();
+
+    if separators.len() != 3 {
+      return Err(Error::InvalidTimecode);
+    }
+
+    let drop_frame = match separators[2].1 {
+      ':' => false,
+      ';' | '.' | ',' => true,
+      _ => return Err(Error::InvalidTimecode),
+    };
+
+    let components = s
+      .split(|c: char| !c.is_ascii_digit())
+      .map(|component| component
+        .parse::<u32>()
+        .map_err(|_| Error::InvalidTimecode))
+      .collect::<Result<Vec<_>>>()?;
+
+    Self::new(
+      components[0],
+      components[1],
+      components[2],
+      components[3],
+      drop_frame,
+    )
+  }
+
+}
+
+impl fmt::Display for Timecode {
+
+  /// Format timecode as `HH:MM:SS:FF`, or `HH:MM:SS;FF` for drop-frame.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{:02}:{:02}:{:02}{}{:02}",
+      self.hours,
+      self.minutes,
+      self.seconds,
+      if self.drop_frame { ';' } else { ':' },
+      self.frames,
+    )
+  }
+
+}
+
+/// Get the nominal (integer) frame rate that is used for counting frames,
+/// like 30 for 29.97. Drop-frame timecode is only defined for multiples of
+/// 30000/1001.
+/// 
+/// # Arguments
+/// 
+/// * `frame_rate` - Frame rate as numerator and denominator.
+/// * `drop_frame` - Whether or not drop-frame timecode is used.
+fn nominal_frame_rate(frame_rate: (i32, i32), drop_frame: bool) -> Result<u64> {
+  let (numerator, denominator) = frame_rate;
+  if numerator <= 0 || denominator <= 0 {
+    return Err(Error::InvalidTimecode);
+  }
+
+  let (numerator, denominator) = (numerator as u64, denominator as u64);
+  let fps = numerator / denominator + u64::from(numerator % denominator > 0);
+
+  if drop_frame && (fps % 30 != 0 || numerator * 1001 != fps * 1000 * denominator) {
+    return Err(Error::InvalidTimecode);
+  }
+
+  Ok(fps)
+}
+
+/// Whether or not frame numbers are dropped at the start of this second in
+/// drop-frame timecode: the first second of every minute that is not a
+/// multiple of ten.
+/// 
+/// # Arguments
+/// 
+/// * `seconds` - Seconds component.
+/// * `minutes` - Minutes component.
+fn is_drop_minute(seconds: u32, minutes: u32) -> bool {
+  seconds == 0 && minutes % 10 >= 1
+}
+
+/// Number of frame numbers that are dropped every minute (except every tenth
+/// minute) in drop-frame timecode: two for 29.97, four for 59.94.
+/// 
+/// # Arguments
+/// 
+/// * `fps` - Nominal frame rate.
+fn drop_frames_per_minute(fps: u64) -> u64 {
+  2 * (fps / 30)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const NTSC: (i32, i32) = (30000, 1001);
+
+  #[test]
+  fn from_frame_number_drop_frame_minute_boundaries() {
+    let cases = [
+      (1799, "00:00:59;29"),
+      (1800, "00:01:00;02"),
+      (3597, "00:01:59;29"),
+      (3598, "00:02:00;02"),
+      (17981, "00:09:59;29"),
+      (17982, "00:10:00;00"),
+      (17983, "00:10:00;01"),
+      (19781, "00:10:59;29"),
+      (19782, "00:11:00;02"),
+      (107892, "01:00:00;00"),
+    ];
+    for (frame_number, expected) in cases {
+      let timecode = Timecode::from_frame_number(frame_number, NTSC, true)
+        .unwrap();
+      assert_eq!(timecode.to_string(), expected);
+      assert_eq!(timecode.to_frame_number(NTSC).unwrap(), frame_number);
+    }
+  }
+
+  #[test]
+  fn from_frame_number_drop_frame_wraps_at_day() {
+    let last = Timecode::from_frame_number(2589407, NTSC, true).unwrap();
+    assert_eq!(last.to_string(), "23:59:59;29");
+    assert_eq!(last.to_frame_number(NTSC).unwrap(), 2589407);
+
+    let wrapped = Timecode::from_frame_number(2589408, NTSC, true).unwrap();
+    assert_eq!(wrapped.to_string(), "00:00:00;00");
+    let wrapped = Timecode::from_frame_number(2589408 + 17982, NTSC, true)
+      .unwrap();
+    assert_eq!(wrapped.to_string(), "00:10:00;00");
+  }
+
+  #[test]
+  fn from_frame_number_non_drop_frame_wraps_at_day() {
+    let last = Timecode::from_frame_number(2591999, (30, 1), false).unwrap();
+    assert_eq!(last.to_string(), "23:59:59:29");
+    let wrapped = Timecode::from_frame_number(2592000, (30, 1), false).unwrap();
+    assert_eq!(wrapped.to_string(), "00:00:00:00");
+  }
+
+  #[test]
+  fn parse_drop_frame() {
+    let timecode = "00:10:00;00".parse::<Timecode>().unwrap();
+    assert!(timecode.is_drop_frame());
+    assert_eq!(timecode.to_frame_number(NTSC).unwrap(), 17982);
+    assert!("00:01:00;00".parse::<Timecode>().is_err());
+    assert!("00:01:00:00".parse::<Timecode>().is_ok());
+  }
+
+  #[test]
+  fn drop_frame_59_94() {
+    const NTSC_DOUBLE: (i32, i32) = (60000, 1001);
+    let cases = [
+      (3599, "00:00:59;59"),
+      (3600, "00:01:00;04"),
+      (35964, "00:10:00;00"),
+      (5178816, "00:00:00;00"),
+    ];
+    for (frame_number, expected) in cases {
+      let timecode = Timecode::from_frame_number(
+          frame_number,
+          NTSC_DOUBLE,
+          true)
+        .unwrap();
+      assert_eq!(timecode.to_string(), expected);
+      if frame_number < 5178816 {
+        assert_eq!(
+          timecode.to_frame_number(NTSC_DOUBLE).unwrap(),
+          frame_number);
+      }
+    }
+
+    // Frame numbers two and three only exist at 29.97.
+    let timecode = "00:01:00;02".parse::<Timecode>().unwrap();
+    assert_eq!(timecode.to_frame_number(NTSC).unwrap(), 1800);
+    assert!(timecode.to_frame_number(NTSC_DOUBLE).is_err());
+    assert!("00:01:00;03".parse::<Timecode>()
+      .unwrap()
+      .to_frame_number(NTSC_DOUBLE)
+      .is_err());
+    assert!("00:10:00;02".parse::<Timecode>()
+      .unwrap()
+      .to_frame_number(NTSC_DOUBLE)
+      .is_ok());
+  }
+
+  #[test]
+  fn drop_frame_requires_ntsc_frame_rate() {
+    assert!(Timecode::from_frame_number(0, (25, 1), true).is_err());
+    assert!(Timecode::from_frame_number(0, (30, 1), true).is_err());
+    assert!(Timecode::from_frame_number(0, (60000, 1001), true).is_ok());
+  }
+
+}